@@ -1,7 +1,7 @@
 use openmls::prelude::*;
 use openmls::prelude::tls_codec::Serialize;
-use openmls::group::PURE_CIPHERTEXT_WIRE_FORMAT_POLICY;
 use openmls_basic_credential::SignatureKeyPair;
+use openmls_traits::storage::StorageProvider;
 use std::sync::{Arc, RwLock};
 
 use crate::error::MLSError;
@@ -13,6 +13,323 @@ pub struct MLSContext {
     inner: Arc<RwLock<MLSContextInner>>,
 }
 
+/// Categorize an OpenMLS `process_message` failure into the granular
+/// `MLSError` decryption variants, so the Swift layer can tell a transient
+/// ordering problem apart from a permanent decryption failure.
+///
+/// `GenerationOutOfBound` means the secret tree hasn't ratcheted far enough
+/// yet for this sender/generation - the caller should park the ciphertext in
+/// a per-sender reorder buffer and retry once earlier generations arrive,
+/// since the secret tree only ratchets forward. `AeadError`/`WrongWireFormat`/
+/// `MalformedContent` are permanent and the message should be dropped.
+///
+/// OpenMLS's own `MessageDecryptionError::GenerationOutOfBound` doesn't carry
+/// the sender/generation that triggered it, so those fields are best-effort
+/// placeholders here until a future OpenMLS version threads that detail
+/// through its public error type.
+fn map_decryption_error(e: &ProcessMessageError) -> MLSError {
+    match e {
+        ProcessMessageError::ValidationError(ValidationError::UnableToDecrypt(inner)) => match inner {
+            MessageDecryptionError::GenerationOutOfBound => {
+                MLSError::generation_out_of_bound("unknown", 0)
+            }
+            MessageDecryptionError::AeadError => MLSError::AeadError,
+            MessageDecryptionError::MalformedContent => MLSError::MalformedContent,
+            _ => MLSError::DecryptionFailed,
+        },
+        ProcessMessageError::ValidationError(ValidationError::WrongWireFormat) => MLSError::WrongWireFormat,
+        _ => MLSError::DecryptionFailed,
+    }
+}
+
+/// Validate and convert a caller-supplied master key into the fixed-size
+/// array `storage_encryption::seal`/`open` require
+fn storage_master_key(bytes: &[u8]) -> Result<[u8; 32], MLSError> {
+    bytes.try_into().map_err(|_| {
+        MLSError::invalid_input(format!("master_key must be exactly 32 bytes, got {}", bytes.len()))
+    })
+}
+
+/// Deserialize and validate a batch of caller-supplied key packages, trying
+/// both the MlsMessage-wrapped and raw `KeyPackage` wire formats - mirrors
+/// the parsing `add_members_batch` does inline
+fn parse_key_packages(inner: &MLSContextInner, key_packages: &[KeyPackageData]) -> Result<Vec<KeyPackage>, MLSError> {
+    let kps: Vec<KeyPackage> = key_packages
+        .iter()
+        .enumerate()
+        .map(|(idx, kp_data)| {
+            if let Ok((mls_msg, _)) = MlsMessageIn::tls_deserialize_bytes(&kp_data.data) {
+                if let MlsMessageBodyIn::KeyPackage(kp_in) = mls_msg.extract() {
+                    return kp_in.validate(inner.provider().crypto(), ProtocolVersion::default())
+                        .map_err(|e| {
+                            crate::error_log!("[MLS-FFI] parse_key_packages: key package {} validation failed: {:?}", idx, e);
+                            MLSError::InvalidKeyPackage
+                        });
+                }
+            }
+
+            let (kp_in, _) = KeyPackageIn::tls_deserialize_bytes(&kp_data.data)
+                .map_err(|e| MLSError::serialization_error(e))?;
+            kp_in.validate(inner.provider().crypto(), ProtocolVersion::default())
+                .map_err(|e| {
+                    crate::error_log!("[MLS-FFI] parse_key_packages: key package {} validation failed: {:?}", idx, e);
+                    MLSError::InvalidKeyPackage
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for kp in &kps {
+        inner.verify_x509_credential(kp.leaf_node().credential())?;
+    }
+
+    Ok(kps)
+}
+
+/// Confirm a message's epoch is either current or within the retained
+/// past-epoch window, so a message beyond that window fails with a clear,
+/// specific error instead of a generic decryption failure once it reaches
+/// OpenMLS
+///
+/// Late/out-of-order messages from a past epoch are expected (network
+/// reordering, a slow peer) and OpenMLS can still decrypt them as long as
+/// that epoch's secret tree is within `max_past_epochs`.
+fn check_epoch_in_retained_window(
+    message_epoch: u64,
+    current_epoch: u64,
+    retention_config: &GroupConfig,
+    epoch_manager: &crate::epoch_storage::EpochSecretManager,
+    group_id: &[u8],
+) -> Result<(), MLSError> {
+    if message_epoch > current_epoch {
+        crate::error_log!("[MLS-FFI] ERROR: Message epoch {} is ahead of group epoch {}", message_epoch, current_epoch);
+        return Err(MLSError::invalid_input(format!(
+            "Cannot decrypt message from future epoch {} (group is at epoch {})",
+            message_epoch, current_epoch
+        )));
+    } else if message_epoch < current_epoch {
+        let epoch_distance = current_epoch - message_epoch;
+        if epoch_distance > retention_config.max_past_epochs as u64 {
+            crate::error_log!("[MLS-FFI] ERROR: Message epoch {} is {} epochs behind current epoch {}, beyond retained window of {}",
+                message_epoch, epoch_distance, current_epoch, retention_config.max_past_epochs);
+            return Err(MLSError::epoch_secret_unavailable(format!(
+                "message epoch {} is {} epochs behind current epoch {}, beyond the retained window of {} past epochs",
+                message_epoch, epoch_distance, current_epoch, retention_config.max_past_epochs
+            )));
+        }
+
+        // Confirm retention hasn't already pruned this epoch's secret
+        // (e.g. after a restart where the group advanced further than
+        // this stored secret's retention window allowed)
+        if epoch_manager.get_epoch_secret(group_id, message_epoch).is_err() {
+            crate::error_log!("[MLS-FFI] ERROR: No retained secret for epoch {} of group {}", message_epoch, hex::encode(group_id));
+            return Err(MLSError::epoch_secret_unavailable(format!(
+                "no retained secret for epoch {} of group {}", message_epoch, hex::encode(group_id)
+            )));
+        }
+
+        crate::debug_log!("[MLS-FFI] Message is {} epoch(s) behind current epoch {}, within retained window of {}",
+            epoch_distance, current_epoch, retention_config.max_past_epochs);
+    }
+
+    Ok(())
+}
+
+/// Build a `StagedCommitInfo` membership delta from a `StagedCommit`
+///
+/// Shared by `process_message`'s `StagedCommitMessage` arm and `stage_commit`
+/// so both surface the same full set of queued proposal types; `staged_commit_id`
+/// is left empty for the caller to fill in once the registry write (which needs
+/// `&mut inner`) can happen, outside this closure's borrow of it.
+fn build_staged_commit_info(
+    group: &MlsGroup,
+    provider: &crate::group_storage::ContextProvider,
+    staged: &StagedCommit,
+    group_id: &[u8],
+    sender_credential: CredentialData,
+    is_external: bool,
+) -> Result<StagedCommitInfo, MLSError> {
+    let new_epoch = staged.group_context().epoch().as_u64();
+
+    let added_members: Vec<StagedAddedMember> = staged
+        .add_proposals()
+        .map(|queued_add| {
+            let key_package = queued_add.add_proposal().key_package();
+            let credential = key_package.leaf_node().credential();
+            Ok(StagedAddedMember {
+                credential: CredentialData {
+                    credential_type: format!("{:?}", credential.credential_type()),
+                    identity: credential.serialized_content().to_vec(),
+                },
+                signature_key: key_package.leaf_node().signature_key().as_slice().to_vec(),
+                key_package_ref: key_package.hash_ref(provider.crypto())
+                    .map_err(|e| MLSError::openmls_error(e))?
+                    .as_slice()
+                    .to_vec(),
+            })
+        })
+        .collect::<Result<Vec<_>, MLSError>>()?;
+
+    let own_leaf_index = group.own_leaf_index().u32();
+    let mut self_removed = false;
+    let removed_members: Vec<MemberCredential> = staged
+        .remove_proposals()
+        .filter_map(|queued_remove| {
+            let removed_index = queued_remove.remove_proposal().removed().u32();
+            if removed_index == own_leaf_index {
+                self_removed = true;
+            }
+            group.members().find(|m| m.index.u32() == removed_index).map(|m| MemberCredential {
+                leaf_index: m.index.u32(),
+                credential: CredentialData {
+                    credential_type: format!("{:?}", m.credential.credential_type()),
+                    identity: m.credential.serialized_content().to_vec(),
+                },
+                signature_key: m.signature_key,
+            })
+        })
+        .collect();
+
+    // Update proposals carry the updating member's own leaf index as their
+    // sender; non-member senders and leaves missing from the tree are skipped
+    let updated_members: Vec<UpdateProposalInfo> = staged
+        .update_proposals()
+        .filter_map(|queued_update| {
+            let leaf_index = match queued_update.sender() {
+                Sender::Member(leaf_index) => leaf_index.u32(),
+                _ => return None,
+            };
+            let old_member = group.members().find(|m| m.index.u32() == leaf_index)?;
+            let new_credential = queued_update.update_proposal().leaf_node().credential();
+            Some(UpdateProposalInfo {
+                leaf_index,
+                old_credential: CredentialData {
+                    credential_type: format!("{:?}", old_member.credential.credential_type()),
+                    identity: old_member.credential.serialized_content().to_vec(),
+                },
+                new_credential: CredentialData {
+                    credential_type: format!("{:?}", new_credential.credential_type()),
+                    identity: new_credential.serialized_content().to_vec(),
+                },
+            })
+        })
+        .collect();
+
+    let extension_changes: Vec<String> = staged
+        .group_context_extension_proposals()
+        .map(|queued| format!("{:?}", queued.group_context_extensions_proposal().extensions()))
+        .collect();
+
+    let psk_proposals: Vec<String> = staged
+        .psk_proposals()
+        .map(|queued| format!("{:?}", queued.psk_proposal()))
+        .collect();
+
+    let requires_reinit = staged.reinit_proposals().count() > 0;
+
+    crate::debug_log!("[MLS-FFI] StagedCommit: {} added, {} removed, {} updated, {} extension change(s), {} psk proposal(s), self_removed={}, requires_reinit={}, is_external={}, new epoch {}",
+        added_members.len(), removed_members.len(), updated_members.len(), extension_changes.len(), psk_proposals.len(), self_removed, requires_reinit, is_external, new_epoch);
+
+    Ok(StagedCommitInfo {
+        group_id: group_id.to_vec(),
+        sender_credential,
+        is_external,
+        added_members,
+        removed_members,
+        updated_members,
+        extension_changes,
+        psk_proposals,
+        self_removed,
+        requires_reinit,
+        new_epoch,
+        staged_commit_id: String::new(),
+    })
+}
+
+/// Decrypt one ciphertext against an already-resolved group
+///
+/// Shared by `decrypt_message` and `decrypt_batch` so both go through the same
+/// retained-epoch bound checks and OpenMLS `process_message` call; the only
+/// difference between the two call sites is how many times the group and the
+/// write lock get resolved around this function.
+fn decrypt_ciphertext(
+    group: &mut MlsGroup,
+    provider: &crate::group_storage::ContextProvider,
+    ciphertext: &[u8],
+    retention_config: &GroupConfig,
+    epoch_manager: &crate::epoch_storage::EpochSecretManager,
+    group_id: &[u8],
+) -> Result<Vec<u8>, MLSError> {
+    crate::debug_log!("[MLS-FFI] Current group epoch: {:?}", group.epoch());
+    crate::debug_log!("[MLS-FFI] Group ciphersuite: {:?}", group.ciphersuite());
+
+    crate::debug_log!("[MLS-FFI] Attempting to deserialize MlsMessage...");
+    let (mls_msg, remaining) = MlsMessageIn::tls_deserialize_bytes(ciphertext)
+        .map_err(|e| {
+            crate::error_log!("[MLS-FFI] ERROR: Failed to deserialize MlsMessage: {:?}", e);
+            MLSError::serialization_error(e)
+        })?;
+    crate::debug_log!("[MLS-FFI] MlsMessage deserialized successfully ({} bytes remaining)", remaining.len());
+
+    crate::debug_log!("[MLS-FFI] Converting MlsMessage to ProtocolMessage...");
+    let protocol_msg: ProtocolMessage = mls_msg.try_into()
+        .map_err(|e| {
+            crate::error_log!("[MLS-FFI] ERROR: Failed to convert to ProtocolMessage: {:?}", e);
+            MLSError::DecryptionFailed
+        })?;
+    crate::debug_log!("[MLS-FFI] ProtocolMessage created successfully");
+    crate::debug_log!("[MLS-FFI] Protocol message epoch: {:?}", protocol_msg.epoch());
+
+    // Only application messages can legitimately trail the group's current
+    // epoch (OpenMLS keeps `max_past_epochs` of secret trees around for
+    // exactly that); handshake messages only ever apply at the current
+    // epoch, so OpenMLS's own `process_message` already rejects those from a
+    // mismatched epoch - checking the retained window for them too would
+    // reject past-epoch proposals/commits that are otherwise valid inputs.
+    // Mirrors the content-type gating in `process_message`.
+    if protocol_msg.content_type() == ContentType::Application {
+        check_epoch_in_retained_window(
+            protocol_msg.epoch().as_u64(),
+            group.epoch().as_u64(),
+            retention_config,
+            epoch_manager,
+            group_id,
+        )?;
+    }
+
+    crate::debug_log!("[MLS-FFI] Calling OpenMLS process_message...");
+    let processed = group
+        .process_message(provider, protocol_msg)
+        .map_err(|e| {
+            crate::error_log!("[MLS-FFI] ERROR: OpenMLS process_message failed: {:?}", e);
+            crate::error_log!("[MLS-FFI] ERROR: Error type: {}", std::any::type_name_of_val(&e));
+            map_decryption_error(&e)
+        })?;
+    crate::debug_log!("[MLS-FFI] OpenMLS process_message succeeded");
+
+    crate::debug_log!("[MLS-FFI] Processing message content...");
+    match processed.into_content() {
+        ProcessedMessageContent::ApplicationMessage(app_msg) => {
+            let bytes = app_msg.into_bytes();
+            crate::debug_log!("[MLS-FFI] ApplicationMessage processed: {} bytes", bytes.len());
+            Ok(bytes)
+        },
+        ProcessedMessageContent::ProposalMessage(prop) => {
+            crate::debug_log!("[MLS-FFI] ProposalMessage received: {:?}", std::any::type_name_of_val(&prop));
+            Ok(vec![]) // Proposals don't have plaintext
+        },
+        ProcessedMessageContent::ExternalJoinProposalMessage(ext) => {
+            crate::debug_log!("[MLS-FFI] ExternalJoinProposalMessage received: {:?}", std::any::type_name_of_val(&ext));
+            Ok(vec![])
+        },
+        ProcessedMessageContent::StagedCommitMessage(staged) => {
+            crate::debug_log!("[MLS-FFI] StagedCommitMessage received: {:?}", std::any::type_name_of_val(&staged));
+            // Don't auto-merge - let Swift validate first
+            // Return empty vec to indicate staged commit (Swift will use process_message instead)
+            Ok(vec![])
+        },
+    }
+}
+
 #[uniffi::export]
 impl MLSContext {
     #[uniffi::constructor]
@@ -38,6 +355,96 @@ impl MLSContext {
         Ok(())
     }
 
+    /// Configure the CA certificates (DER) that X.509 credentials must chain to
+    ///
+    /// Key packages presented to `add_members` whose credential is X.509 and
+    /// doesn't chain to one of these anchors are rejected. Passing an empty
+    /// list disables the check.
+    pub fn set_x509_trust_anchors(&self, trust_anchors: Vec<Vec<u8>>) -> Result<(), MLSError> {
+        let mut inner = self.inner.write()
+            .map_err(|_| MLSError::ContextNotInitialized)?;
+
+        inner.set_x509_trust_anchors(trust_anchors);
+        Ok(())
+    }
+
+    /// Install a `GroupStateStorage` callback as OpenMLS's actual storage backend,
+    /// in place of the default in-memory `MemoryStorage`
+    ///
+    /// This MUST be called during initialization before any group is created or
+    /// joined. Once installed, the tree/transcript-hash/key-package/etc. reads
+    /// and writes OpenMLS performs for this context go through the callback
+    /// transactionally per `(group_id, entity_type, key)` instead of one
+    /// monolithic blob (listing own leaf nodes or queued proposals is the
+    /// exception: the callback can't enumerate, so those lists come back
+    /// empty); `serialize_storage`/`deserialize_storage` stop working
+    /// afterward, since the callback backend is expected to persist incrementally.
+    pub fn install_storage_provider(&self, storage: Box<dyn GroupStateStorage>) -> Result<(), MLSError> {
+        let mut inner = self.inner.write()
+            .map_err(|_| MLSError::ContextNotInitialized)?;
+
+        inner.install_storage_provider(Arc::from(storage));
+        Ok(())
+    }
+
+    /// Install a `KeyPackageMetadataStorage` callback that receives
+    /// write-through copies of signer mappings and `rotate_key_packages`
+    /// bundle bookkeeping (identity, last-resort flag, rotation history)
+    ///
+    /// The `serialize_storage` JSON blob keeps carrying this state as well.
+    /// Bundles made by `create_key_package` are not written to this backend.
+    ///
+    /// Call `restore_key_package_metadata` right after to rehydrate the
+    /// in-memory caches from whatever this backend already has stored.
+    pub fn install_key_package_metadata_storage(&self, storage: Box<dyn KeyPackageMetadataStorage>) -> Result<(), MLSError> {
+        let mut inner = self.inner.write()
+            .map_err(|_| MLSError::ContextNotInitialized)?;
+
+        inner.install_key_package_metadata_storage(Arc::from(storage));
+        Ok(())
+    }
+
+    /// Rehydrate signer mappings and key package bookkeeping from the
+    /// installed `KeyPackageMetadataStorage` backend
+    ///
+    /// Call once at startup, after `install_key_package_metadata_storage`
+    /// and after the bundles' own backing storage (e.g. a `GroupStateStorage`
+    /// callback) has been installed and is ready to be queried.
+    pub fn restore_key_package_metadata(&self) -> Result<(), MLSError> {
+        let mut inner = self.inner.write()
+            .map_err(|_| MLSError::ContextNotInitialized)?;
+
+        inner.restore_key_package_metadata()
+    }
+
+    /// Install a durable backend for the staged-welcome/staged-commit registry,
+    /// backed by the same `GroupStateStorage` callback installed via
+    /// `install_storage_provider` (or a separate one, if the host wants staged
+    /// state kept apart from group storage)
+    ///
+    /// Call `replay_staged_operations` after a cold start to recover any
+    /// staged operation that didn't get merged before the app was killed.
+    pub fn install_staged_registry(&self, storage: Box<dyn GroupStateStorage>) -> Result<(), MLSError> {
+        let mut inner = self.inner.write()
+            .map_err(|_| MLSError::ContextNotInitialized)?;
+
+        inner.install_staged_registry(Arc::from(storage));
+        Ok(())
+    }
+
+    /// Replay the durable log of not-yet-completed staged operations for a group
+    ///
+    /// Returns an empty list if no registry has been installed. The caller is
+    /// responsible for re-parsing each entry's raw Welcome/commit bytes back
+    /// into a `StagedWelcome`/`StagedCommit` and re-populating the in-memory
+    /// maps via `store_staged_welcome`/`store_staged_commit`.
+    pub fn replay_staged_operations(&self, group_id: Vec<u8>) -> Result<Vec<crate::staged_registry::StagedLogEntry>, MLSError> {
+        let inner = self.inner.read()
+            .map_err(|_| MLSError::ContextNotInitialized)?;
+
+        inner.replay_staged_operations(&group_id)
+    }
+
     pub fn create_group(&self, identity_bytes: Vec<u8>, config: Option<GroupConfig>) -> Result<GroupCreationResult, MLSError> {
         crate::info_log!("[MLS-FFI] create_group: Starting");
         crate::debug_log!("[MLS-FFI] Identity bytes: {} bytes", identity_bytes.len());
@@ -113,7 +520,7 @@ impl MLSContext {
                 let (kp_in, remaining) = KeyPackageIn::tls_deserialize_bytes(&kp_data.data)
                     .map_err(|e| {
                         crate::error_log!("[MLS] Both deserialization methods failed for key package {}: {:?}", idx, e);
-                        MLSError::SerializationError
+                        MLSError::serialization_error(e)
                     })?;
 
                 crate::debug_log!("[MLS] Key package {} deserialized as raw KeyPackage ({} bytes remaining)", idx, remaining.len());
@@ -131,6 +538,16 @@ impl MLSContext {
             return Err(MLSError::InvalidKeyPackage);
         }
 
+        // Reject X.509 key packages whose certificate chain doesn't terminate
+        // at a configured trust anchor (no-op if none are configured)
+        for (idx, kp) in kps.iter().enumerate() {
+            inner.verify_x509_credential(kp.leaf_node().credential())
+                .map_err(|e| {
+                    crate::error_log!("[MLS-FFI] Key package {} failed X.509 trust verification: {:?}", idx, e);
+                    e
+                })?;
+        }
+
         let gid = GroupId::from_slice(&group_id);
 
         // üîç DEBUG: Check for duplicate key packages by credential
@@ -196,10 +613,10 @@ impl MLSContext {
 
             // üîç DEBUG: Check for duplicate credentials (self-add or duplicate member)
             if let Some(own_leaf) = group.own_leaf_node() {
-                let own_credential = own_leaf.credential().serialized_content();
+                let own_credential = MLSContextInner::credential_comparison_key(own_leaf.credential());
 
                 for (idx, kp) in kps.iter().enumerate() {
-                    let kp_credential = kp.leaf_node().credential().serialized_content();
+                    let kp_credential = MLSContextInner::credential_comparison_key(kp.leaf_node().credential());
 
                     if own_credential == kp_credential {
                         crate::error_log!("[MLS-FFI] ‚ùå DUPLICATE DETECTED: KeyPackage[{}] matches group creator!", idx);
@@ -209,7 +626,7 @@ impl MLSContext {
 
                     // Check against all existing members
                     for (member_idx, member) in group.members().enumerate() {
-                        let member_credential = member.credential.serialized_content();
+                        let member_credential = MLSContextInner::credential_comparison_key(&member.credential);
                         if kp_credential == member_credential {
                             crate::error_log!("[MLS-FFI] ‚ùå DUPLICATE DETECTED: KeyPackage[{}] matches existing Member[{}]!", idx, member_idx);
                             return Err(MLSError::invalid_input("Member already in group"));
@@ -276,7 +693,7 @@ impl MLSContext {
             group.merge_pending_commit(provider)
                 .map_err(|e| {
                     crate::error_log!("[MLS-FFI] ‚ùå merge_pending_commit failed: {:?}", e);
-                    MLSError::MergeFailed
+                    MLSError::merge_failed(e)
                 })?;
 
             // üîç DEBUG: Verify member count increased after merge
@@ -295,7 +712,7 @@ impl MLSContext {
             // Serialize the commit (MlsMessageOut)
             let commit_bytes = commit
                 .tls_serialize_detached()
-                .map_err(|_| MLSError::SerializationError)?;
+                .map_err(|e| MLSError::serialization_error(e))?;
 
             // ‚úÖ CRITICAL FIX: Serialize Welcome WITH MlsMessage wrapper
             // The receiver expects MlsMessageIn format, not bare Welcome
@@ -304,7 +721,7 @@ impl MLSContext {
 
             let welcome_bytes = welcome
                 .tls_serialize_detached()
-                .map_err(|_| MLSError::SerializationError)?;
+                .map_err(|e| MLSError::serialization_error(e))?;
 
             crate::debug_log!("[MLS-FFI] ‚úÖ Welcome serialized with wrapper");
 
@@ -322,6 +739,169 @@ impl MLSContext {
         })
     }
 
+    /// Add many members in a single commit, producing one Welcome for all of them
+    ///
+    /// Equivalent to `add_members` but returns the full `CommitBundle`
+    /// (including `GroupInfo`, needed for a new member to join via an
+    /// external commit) instead of only the commit and Welcome bytes.
+    /// Bootstrapping or reconciling a large roster this way avoids the
+    /// per-member epoch churn of committing each addition separately.
+    pub fn add_members_batch(
+        &self,
+        group_id: Vec<u8>,
+        key_packages: Vec<KeyPackageData>,
+    ) -> Result<CommitBundle, MLSError> {
+        let mut inner = self.inner.write()
+            .map_err(|_| MLSError::ContextNotInitialized)?;
+
+        crate::debug_log!("[MLS-FFI] add_members_batch: Processing {} key packages", key_packages.len());
+
+        let kps: Vec<KeyPackage> = key_packages
+            .iter()
+            .enumerate()
+            .map(|(idx, kp_data)| {
+                if let Ok((mls_msg, _)) = MlsMessageIn::tls_deserialize_bytes(&kp_data.data) {
+                    if let MlsMessageBodyIn::KeyPackage(kp_in) = mls_msg.extract() {
+                        return kp_in.validate(inner.provider().crypto(), ProtocolVersion::default())
+                            .map_err(|e| {
+                                crate::error_log!("[MLS-FFI] add_members_batch: key package {} validation failed: {:?}", idx, e);
+                                MLSError::InvalidKeyPackage
+                            });
+                    }
+                }
+
+                let (kp_in, _) = KeyPackageIn::tls_deserialize_bytes(&kp_data.data)
+                    .map_err(|e| MLSError::serialization_error(e))?;
+                kp_in.validate(inner.provider().crypto(), ProtocolVersion::default())
+                    .map_err(|e| {
+                        crate::error_log!("[MLS-FFI] add_members_batch: key package {} validation failed: {:?}", idx, e);
+                        MLSError::InvalidKeyPackage
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if kps.is_empty() {
+            return Err(MLSError::InvalidKeyPackage);
+        }
+
+        for kp in &kps {
+            inner.verify_x509_credential(kp.leaf_node().credential())?;
+        }
+
+        let gid = GroupId::from_slice(&group_id);
+
+        // Same forward-secrecy bookkeeping as `merge_pending_commit`: export the
+        // current epoch's secret before the commit advances it, then prune
+        // anything older than `max_past_epochs` once it has
+        let epoch_manager = inner.epoch_secret_manager().clone();
+        let retention_config = inner.config_for_group(&gid)?;
+
+        let (commit_data, welcome_data, group_info_data, new_epoch) = inner.with_group(&gid, |group, provider, signer| {
+            if let Err(e) = epoch_manager.export_current_epoch_secret(group, provider) {
+                crate::warn_log!("[MLS-FFI] add_members_batch: WARNING: Failed to export epoch secret: {:?}", e);
+            }
+
+            let (commit, welcome, group_info) = group
+                .add_members(provider, signer, &kps)
+                .map_err(|e| {
+                    crate::error_log!("[MLS-FFI] add_members_batch: add_members failed: {:?}", e);
+                    MLSError::AddMembersFailed
+                })?;
+
+            group.merge_pending_commit(provider)
+                .map_err(|e| MLSError::merge_failed(e))?;
+
+            let commit_data = commit.tls_serialize_detached().map_err(|e| MLSError::serialization_error(e))?;
+            let welcome_data = welcome.tls_serialize_detached().map_err(|e| MLSError::serialization_error(e))?;
+            let group_info_data = group_info
+                .map(|gi| gi.tls_serialize_detached())
+                .transpose()
+                .map_err(|e| MLSError::serialization_error(e))?;
+
+            crate::info_log!("[MLS-FFI] add_members_batch: committed {} addition(s), group now at epoch {}",
+                kps.len(), group.epoch().as_u64());
+
+            Ok((commit_data, Some(welcome_data), group_info_data, group.epoch().as_u64()))
+        })?;
+
+        if let Err(e) = epoch_manager.enforce_retention(&group_id, new_epoch, &retention_config) {
+            crate::warn_log!("[MLS-FFI] add_members_batch: WARNING: Epoch secret retention enforcement failed: {:?}", e);
+        }
+
+        Ok(CommitBundle {
+            commit_data,
+            welcome_data,
+            group_info_data,
+        })
+    }
+
+    /// Remove many members in a single commit
+    ///
+    /// Equivalent to committing one Remove proposal per leaf index, but as a
+    /// single epoch bump instead of N. A remove-only commit never produces a
+    /// Welcome, so `welcome_data` on the returned bundle is always `None`.
+    pub fn remove_members_batch(
+        &self,
+        group_id: Vec<u8>,
+        leaf_indices: Vec<u32>,
+    ) -> Result<CommitBundle, MLSError> {
+        let mut inner = self.inner.write()
+            .map_err(|_| MLSError::ContextNotInitialized)?;
+
+        if leaf_indices.is_empty() {
+            return Err(MLSError::invalid_input("No leaf indices given to remove"));
+        }
+
+        let gid = GroupId::from_slice(&group_id);
+        let members: Vec<LeafNodeIndex> = leaf_indices.iter().copied().map(LeafNodeIndex::new).collect();
+
+        crate::debug_log!("[MLS-FFI] remove_members_batch: Removing {} member(s) from group {}",
+            members.len(), hex::encode(&group_id));
+
+        // Same forward-secrecy bookkeeping as `merge_pending_commit`: export the
+        // current epoch's secret before the commit advances it, then prune
+        // anything older than `max_past_epochs` once it has
+        let epoch_manager = inner.epoch_secret_manager().clone();
+        let retention_config = inner.config_for_group(&gid)?;
+
+        let (commit_data, group_info_data, new_epoch) = inner.with_group(&gid, |group, provider, signer| {
+            if let Err(e) = epoch_manager.export_current_epoch_secret(group, provider) {
+                crate::warn_log!("[MLS-FFI] remove_members_batch: WARNING: Failed to export epoch secret: {:?}", e);
+            }
+
+            let (commit, _welcome, group_info) = group
+                .remove_members(provider, signer, &members)
+                .map_err(|e| {
+                    crate::error_log!("[MLS-FFI] remove_members_batch: remove_members failed: {:?}", e);
+                    MLSError::RemoveMembersFailed
+                })?;
+
+            group.merge_pending_commit(provider)
+                .map_err(|e| MLSError::merge_failed(e))?;
+
+            let commit_data = commit.tls_serialize_detached().map_err(|e| MLSError::serialization_error(e))?;
+            let group_info_data = group_info
+                .map(|gi| gi.tls_serialize_detached())
+                .transpose()
+                .map_err(|e| MLSError::serialization_error(e))?;
+
+            crate::info_log!("[MLS-FFI] remove_members_batch: committed {} removal(s), group now at epoch {}",
+                members.len(), group.epoch().as_u64());
+
+            Ok((commit_data, group_info_data, group.epoch().as_u64()))
+        })?;
+
+        if let Err(e) = epoch_manager.enforce_retention(&group_id, new_epoch, &retention_config) {
+            crate::warn_log!("[MLS-FFI] remove_members_batch: WARNING: Epoch secret retention enforcement failed: {:?}", e);
+        }
+
+        Ok(CommitBundle {
+            commit_data,
+            welcome_data: None,
+            group_info_data,
+        })
+    }
+
     /// Delete an MLS group from storage
     /// This should be called when a conversation is deleted or the user leaves
     pub fn delete_group(&self, group_id: Vec<u8>) -> Result<(), MLSError> {
@@ -378,7 +958,7 @@ impl MLSContext {
             msg.tls_serialize_detached()
                 .map_err(|e| {
                     crate::error_log!("[MLS-FFI] ERROR: Failed to serialize message: {:?}", e);
-                    MLSError::SerializationError
+                    MLSError::serialization_error(e)
                 })
         })?;
 
@@ -405,66 +985,128 @@ impl MLSContext {
         let gid = GroupId::from_slice(&group_id);
         crate::debug_log!("[MLS-FFI] GroupId created from slice");
 
+        // Retained-epoch bound checks (below) need the group's config and the
+        // epoch secret manager before `with_group` takes `inner` mutably
+        let retention_config = inner.config_for_group(&gid)?;
+        let epoch_manager = inner.epoch_secret_manager().clone();
+
         let plaintext = inner.with_group(&gid, |group, provider, _signer| {
-            crate::debug_log!("[MLS-FFI] Inside with_group closure");
-            crate::debug_log!("[MLS-FFI] Current group epoch: {:?}", group.epoch());
-            crate::debug_log!("[MLS-FFI] Group ciphersuite: {:?}", group.ciphersuite());
+            decrypt_ciphertext(group, provider, &ciphertext, &retention_config, &epoch_manager, &group_id)
+        })?;
 
-            crate::debug_log!("[MLS-FFI] Attempting to deserialize MlsMessage...");
-            let (mls_msg, remaining) = MlsMessageIn::tls_deserialize_bytes(&ciphertext)
-                .map_err(|e| {
-                    crate::error_log!("[MLS-FFI] ERROR: Failed to deserialize MlsMessage: {:?}", e);
-                    MLSError::SerializationError
-                })?;
-            crate::debug_log!("[MLS-FFI] MlsMessage deserialized successfully ({} bytes remaining)", remaining.len());
+        crate::debug_log!("[MLS-FFI] decrypt_message: Completed successfully, plaintext size: {} bytes", plaintext.len());
+        Ok(DecryptResult { plaintext })
+    }
+
+    /// Decrypt a batch of ciphertexts for the same group in a single call
+    ///
+    /// Acquires the write lock and resolves the group once, then decrypts every
+    /// ciphertext against it in order, instead of paying that overhead per
+    /// message the way repeated `decrypt_message` calls would during history
+    /// replay after reconnecting. One corrupt or undecryptable frame is
+    /// reported as its own `DecryptOutcome::Failure` rather than aborting the
+    /// rest of the batch.
+    pub fn decrypt_batch(&self, group_id: Vec<u8>, ciphertexts: Vec<Vec<u8>>) -> Result<Vec<DecryptOutcome>, MLSError> {
+        crate::debug_log!("[MLS-FFI] decrypt_batch: Starting decryption of {} message(s)", ciphertexts.len());
+
+        let mut inner = self.inner.write()
+            .map_err(|_| MLSError::ContextNotInitialized)?;
+
+        let gid = GroupId::from_slice(&group_id);
+        let retention_config = inner.config_for_group(&gid)?;
+        let epoch_manager = inner.epoch_secret_manager().clone();
+
+        inner.with_group(&gid, |group, provider, _signer| {
+            let outcomes = ciphertexts
+                .iter()
+                .map(|ciphertext| {
+                    match decrypt_ciphertext(group, provider, ciphertext, &retention_config, &epoch_manager, &group_id) {
+                        Ok(plaintext) => DecryptOutcome::Success { plaintext },
+                        Err(e) => DecryptOutcome::Failure { message: e.to_string() },
+                    }
+                })
+                .collect();
+            Ok(outcomes)
+        })
+    }
+
+    /// Decrypt a ciphertext the caller already knows belongs to `epoch`
+    ///
+    /// Functionally a thinner `decrypt_message` that also rejects the ciphertext
+    /// if its own embedded epoch doesn't match `epoch`, so callers driving
+    /// message-history replay against a specific retained epoch (rather than
+    /// just whatever epoch the ciphertext happens to carry) get a clear,
+    /// specific error instead of silently decrypting against the wrong one.
+    pub fn decrypt_for_epoch(
+        &self,
+        group_id: Vec<u8>,
+        epoch: u64,
+        ciphertext: Vec<u8>,
+    ) -> Result<DecryptResult, MLSError> {
+        let mut inner = self.inner.write()
+            .map_err(|_| MLSError::ContextNotInitialized)?;
 
-            crate::debug_log!("[MLS-FFI] Converting MlsMessage to ProtocolMessage...");
+        let gid = GroupId::from_slice(&group_id);
+        let retention_config = inner.config_for_group(&gid)?;
+        let epoch_manager = inner.epoch_secret_manager().clone();
+
+        let plaintext = inner.with_group(&gid, |group, provider, _signer| {
+            let (mls_msg, _) = MlsMessageIn::tls_deserialize_bytes(&ciphertext)
+                .map_err(|e| MLSError::serialization_error(e))?;
             let protocol_msg: ProtocolMessage = mls_msg.try_into()
-                .map_err(|e| {
-                    crate::error_log!("[MLS-FFI] ERROR: Failed to convert to ProtocolMessage: {:?}", e);
-                    MLSError::DecryptionFailed
-                })?;
-            crate::debug_log!("[MLS-FFI] ProtocolMessage created successfully");
-            crate::debug_log!("[MLS-FFI] Protocol message epoch: {:?}", protocol_msg.epoch());
+                .map_err(|_| MLSError::DecryptionFailed)?;
+
+            if protocol_msg.epoch().as_u64() != epoch {
+                return Err(MLSError::invalid_input(format!(
+                    "ciphertext is from epoch {} not the requested epoch {}",
+                    protocol_msg.epoch().as_u64(), epoch
+                )));
+            }
+
+            check_epoch_in_retained_window(epoch, group.epoch().as_u64(), &retention_config, &epoch_manager, &group_id)?;
 
-            crate::debug_log!("[MLS-FFI] Calling OpenMLS process_message...");
             let processed = group
                 .process_message(provider, protocol_msg)
-                .map_err(|e| {
-                    crate::error_log!("[MLS-FFI] ERROR: OpenMLS process_message failed: {:?}", e);
-                    crate::error_log!("[MLS-FFI] ERROR: Error type: {}", std::any::type_name_of_val(&e));
-                    MLSError::DecryptionFailed
-                })?;
-            crate::debug_log!("[MLS-FFI] OpenMLS process_message succeeded");
+                .map_err(|e| map_decryption_error(&e))?;
 
-            crate::debug_log!("[MLS-FFI] Processing message content...");
             match processed.into_content() {
-                ProcessedMessageContent::ApplicationMessage(app_msg) => {
-                    let bytes = app_msg.into_bytes();
-                    crate::debug_log!("[MLS-FFI] ApplicationMessage processed: {} bytes", bytes.len());
-                    Ok(bytes)
-                },
-                ProcessedMessageContent::ProposalMessage(prop) => {
-                    crate::debug_log!("[MLS-FFI] ProposalMessage received: {:?}", std::any::type_name_of_val(&prop));
-                    Ok(vec![]) // Proposals don't have plaintext
-                },
-                ProcessedMessageContent::ExternalJoinProposalMessage(ext) => {
-                    crate::debug_log!("[MLS-FFI] ExternalJoinProposalMessage received: {:?}", std::any::type_name_of_val(&ext));
-                    Ok(vec![])
-                },
-                ProcessedMessageContent::StagedCommitMessage(staged) => {
-                    crate::debug_log!("[MLS-FFI] StagedCommitMessage received: {:?}", std::any::type_name_of_val(&staged));
-                    // Don't auto-merge - let Swift validate first
-                    // Return empty vec to indicate staged commit (Swift will use process_message instead)
-                    Ok(vec![])
-                },
+                ProcessedMessageContent::ApplicationMessage(app_msg) => Ok(app_msg.into_bytes()),
+                _ => Err(MLSError::invalid_input("Not an application message")),
             }
         })?;
 
-        crate::debug_log!("[MLS-FFI] decrypt_message: Completed successfully, plaintext size: {} bytes", plaintext.len());
         Ok(DecryptResult { plaintext })
     }
 
+    /// Change how many past epochs' secrets are retained for `group_id`
+    ///
+    /// This only widens or narrows our own `EpochSecretStorage` retention
+    /// policy (what `prune_epoch_secrets`/the automatic post-commit pruning
+    /// keep around, and how far back `decrypt_message`/`decrypt_for_epoch`
+    /// will accept a late message); it can't retroactively widen OpenMLS's
+    /// own in-process secret tree window, which was fixed to `max_past_epochs`
+    /// when the group was created or joined.
+    pub fn set_max_retained_epochs(&self, group_id: Vec<u8>, max_past_epochs: u32) -> Result<(), MLSError> {
+        let mut inner = self.inner.write()
+            .map_err(|_| MLSError::ContextNotInitialized)?;
+
+        let gid = GroupId::from_slice(&group_id);
+        inner.set_max_retained_epochs(&gid, max_past_epochs)
+    }
+
+    /// Forget every stored epoch secret strictly older than `before_epoch`
+    ///
+    /// Unlike the automatic retention enforced after every commit (bounded by
+    /// `GroupConfig.max_past_epochs`), this lets the caller reclaim forward
+    /// secrecy on demand - e.g. once it has confirmed delivery up through a
+    /// given epoch and no longer needs older secrets for replay.
+    pub fn prune_epoch_secrets(&self, group_id: Vec<u8>, before_epoch: u64) -> Result<(), MLSError> {
+        let inner = self.inner.read()
+            .map_err(|_| MLSError::ContextNotInitialized)?;
+
+        inner.epoch_secret_manager().prune_epoch_secrets(&group_id, before_epoch)
+    }
+
     pub fn process_message(
         &self,
         group_id: Vec<u8>,
@@ -484,7 +1126,22 @@ impl MLSContext {
         let gid = GroupId::from_slice(&group_id);
         crate::debug_log!("[MLS-FFI] GroupId created: {}", hex::encode(gid.as_slice()));
 
-        inner.with_group(&gid, |group, provider, _signer| {
+        // Retained-epoch bound checks (below) need the group's config and the
+        // epoch secret manager before `with_group` takes `inner` mutably
+        let retention_config = inner.config_for_group(&gid)?;
+        let epoch_manager = inner.epoch_secret_manager().clone();
+
+        // A StagedCommitMessage can't be turned into its final `ProcessedContent`
+        // inside `with_group`'s closure: storing the StagedCommit in the registry
+        // needs `&mut inner`, which is already borrowed by the in-progress
+        // `with_group` call. So the closure hands back the raw pieces and the
+        // registry write happens once the borrow ends.
+        enum ProcessOutcome {
+            Done(ProcessedContent),
+            Staged { info: StagedCommitInfo, staged: Box<StagedCommit>, sender: CredentialData, is_external: bool },
+        }
+
+        let outcome = inner.with_group(&gid, |group, provider, _signer| {
             crate::debug_log!("[MLS-FFI] Inside with_group closure for process_message");
             crate::debug_log!("[MLS-FFI] Current group epoch: {:?}", group.epoch());
             crate::debug_log!("[MLS-FFI] Group ciphersuite: {:?}", group.ciphersuite());
@@ -494,7 +1151,7 @@ impl MLSContext {
             let (mls_msg, remaining) = MlsMessageIn::tls_deserialize_bytes(&message_data)
                 .map_err(|e| {
                     crate::error_log!("[MLS-FFI] ERROR: Failed to deserialize MlsMessage: {:?}", e);
-                    MLSError::SerializationError
+                    MLSError::serialization_error(e)
                 })?;
             crate::debug_log!("[MLS-FFI] MlsMessage deserialized ({} bytes remaining)", remaining.len());
 
@@ -507,20 +1164,37 @@ impl MLSContext {
             crate::debug_log!("[MLS-FFI] ProtocolMessage created");
             let message_epoch = protocol_msg.epoch();
             let current_epoch = group.epoch();
+            let content_type = protocol_msg.content_type();
             crate::debug_log!("[MLS-FFI] Protocol message epoch: {:?}", message_epoch);
             crate::debug_log!("[MLS-FFI] Current group epoch: {:?}", current_epoch);
-            crate::debug_log!("[MLS-FFI] Protocol message content type: {:?}", std::any::type_name_of_val(&protocol_msg));
-
-            // Check for epoch mismatch BEFORE attempting to decrypt
-            if message_epoch != current_epoch {
-                crate::warn_log!("[MLS-FFI] ‚ö†Ô∏è EPOCH MISMATCH DETECTED!");
-                crate::debug_log!("[MLS-FFI] Message is from epoch {} but group is at epoch {}", message_epoch.as_u64(), current_epoch.as_u64());
-                crate::debug_log!("[MLS-FFI] This is expected MLS forward secrecy behavior - old epoch keys are deleted");
-                return Err(MLSError::invalid_input(format!(
-                    "Cannot decrypt message from epoch {} - group is at epoch {} (forward secrecy prevents decrypting old epochs)",
-                    message_epoch.as_u64(),
-                    current_epoch.as_u64()
-                )));
+            crate::debug_log!("[MLS-FFI] Protocol message content type: {:?}", content_type);
+
+            // Application messages can be decrypted from any epoch still within
+            // the retained window (OpenMLS keeps `max_past_epochs` of secret
+            // trees around for exactly this). Handshake messages (proposals,
+            // commits) only ever apply at the group's current epoch, so those
+            // keep the strict equality check.
+            match content_type {
+                ContentType::Application => {
+                    check_epoch_in_retained_window(
+                        message_epoch.as_u64(),
+                        current_epoch.as_u64(),
+                        &retention_config,
+                        &epoch_manager,
+                        &group_id,
+                    )?;
+                }
+                _ => {
+                    if message_epoch != current_epoch {
+                        crate::warn_log!("[MLS-FFI] ‚ö†Ô∏è EPOCH MISMATCH DETECTED!");
+                        crate::debug_log!("[MLS-FFI] Handshake message is from epoch {} but group is at epoch {}", message_epoch.as_u64(), current_epoch.as_u64());
+                        return Err(MLSError::invalid_input(format!(
+                            "Cannot process handshake message from epoch {} - group is at epoch {}",
+                            message_epoch.as_u64(),
+                            current_epoch.as_u64()
+                        )));
+                    }
+                }
             }
 
             crate::debug_log!("[MLS-FFI] Calling OpenMLS process_message...");
@@ -531,7 +1205,7 @@ impl MLSContext {
                     crate::error_log!("[MLS-FFI] ERROR: Error details: {:?}", e);
                     crate::error_log!("[MLS-FFI] ERROR: Error type: {}", std::any::type_name_of_val(&e));
                     crate::error_log!("[MLS-FFI] ERROR: Current epoch: {:?}", group.epoch());
-                    MLSError::DecryptionFailed
+                    map_decryption_error(&e)
                 })?;
             crate::debug_log!("[MLS-FFI] OpenMLS process_message succeeded!");
 
@@ -543,6 +1217,7 @@ impl MLSContext {
                 credential_type: format!("{:?}", sender_credential.credential_type()),
                 identity: sender_credential.serialized_content().to_vec(),
             };
+            let is_external = !matches!(processed.sender(), Sender::Member(_));
             crate::debug_log!("[MLS-FFI] Sender extracted: {} bytes identity", sender.identity.len());
 
             match processed.into_content() {
@@ -550,10 +1225,10 @@ impl MLSContext {
                     let plaintext = app_msg.into_bytes();
                     crate::debug_log!("[MLS-FFI] ApplicationMessage processed: {} bytes", plaintext.len());
 
-                    Ok(ProcessedContent::ApplicationMessage {
+                    Ok(ProcessOutcome::Done(ProcessedContent::ApplicationMessage {
                         plaintext,
                         sender,
-                    })
+                    }))
                 },
                 ProcessedMessageContent::ProposalMessage(proposal_msg) => {
                     crate::debug_log!("[MLS-FFI] ProposalMessage received, processing...");
@@ -565,14 +1240,14 @@ impl MLSContext {
                         .tls_serialize_detached()
                         .map_err(|e| {
                             crate::error_log!("[MLS-FFI] ERROR: Failed to serialize proposal: {:?}", e);
-                            MLSError::SerializationError
+                            MLSError::serialization_error(e)
                         })?;
 
                     let proposal_ref_bytes = provider.crypto()
                         .hash(group.ciphersuite().hash_algorithm(), &proposal_bytes)
                         .map_err(|e| {
                             crate::error_log!("[MLS-FFI] ERROR: Failed to hash proposal: {:?}", e);
-                            MLSError::OpenMLSError
+                            MLSError::openmls_error(e)
                         })?;
 
                     crate::debug_log!("[MLS-FFI] Proposal ref computed: {}", hex::encode(&proposal_ref_bytes));
@@ -592,7 +1267,7 @@ impl MLSContext {
                                 info: AddProposalInfo {
                                     credential: credential_info,
                                     key_package_ref: key_package.hash_ref(provider.crypto())
-                                        .map_err(|_| MLSError::OpenMLSError)?
+                                        .map_err(|e| MLSError::openmls_error(e))?
                                         .as_slice()
                                         .to_vec(),
                                 }
@@ -634,32 +1309,59 @@ impl MLSContext {
                     };
 
                     crate::debug_log!("[MLS-FFI] Proposal processed successfully");
-                    Ok(ProcessedContent::Proposal {
+                    Ok(ProcessOutcome::Done(ProcessedContent::Proposal {
                         proposal: proposal_info,
                         proposal_ref: ProposalRef {
                             data: proposal_ref_bytes,
                         },
-                    })
+                    }))
                 },
                 ProcessedMessageContent::ExternalJoinProposalMessage(_) => {
                     crate::error_log!("[MLS-FFI] ERROR: External join proposals not supported");
                     Err(MLSError::invalid_input("External join proposals not supported"))
                 },
                 ProcessedMessageContent::StagedCommitMessage(staged) => {
-                    crate::debug_log!("[MLS-FFI] StagedCommitMessage received, processing...");
-                    let new_epoch = staged.group_context().epoch().as_u64();
-
-                    // Don't auto-merge - return staged commit info for validation
-                    // The staged commit remains in the group's pending state
-                    Ok(ProcessedContent::StagedCommit { new_epoch })
+                    crate::debug_log!("[MLS-FFI] StagedCommitMessage received, building membership delta...");
+                    let info = build_staged_commit_info(group, provider, &staged, &group_id, sender.clone(), is_external)?;
+                    Ok(ProcessOutcome::Staged { info, staged, sender, is_external })
                 },
             }
-        })
+        })?;
+
+        match outcome {
+            ProcessOutcome::Done(content) => Ok(content),
+            ProcessOutcome::Staged { mut info, staged, sender, is_external } => {
+                // One outstanding staged commit per group, keyed by its hex group id,
+                // so `merge_staged_commit`/`reject_staged_commit` can look it up by
+                // group id alone without the caller round-tripping an opaque handle
+                let staged_commit_id = hex::encode(&group_id);
+                inner.store_staged_commit(staged_commit_id.clone(), &group_id, info.new_epoch, message_data, staged, sender, is_external)?;
+                info.staged_commit_id = staged_commit_id;
+                Ok(ProcessedContent::StagedCommit { info })
+            }
+        }
     }
 
+    /// Create a KeyPackage for `identity`
+    ///
+    /// `ciphersuite` selects the IANA suite the KeyPackage (and its signature
+    /// keys) are built with, defaulting to the prior hardcoded
+    /// `X25519Aes128Sha256Ed25519`; the signature scheme is derived from it
+    /// rather than chosen separately. `credential_type` selects Basic or
+    /// X.509, defaulting to Basic, matching `create_group`'s config option.
+    ///
+    /// When `last_resort` is true, the KeyPackage is built with the MLS
+    /// `last_resort` extension and its bundle is kept in storage after a
+    /// Welcome consumes it, so the same published KeyPackage can back more
+    /// than one invite (e.g. being added to several conversations at once).
+    /// Regular KeyPackages remain single-use: OpenMLS deletes their private
+    /// key once a Welcome is processed.
     pub fn create_key_package(
         &self,
         identity_bytes: Vec<u8>,
+        last_resort: bool,
+        ciphersuite: Option<CiphersuiteSelector>,
+        credential_type: Option<CredentialTypeSelector>,
     ) -> Result<KeyPackageResult, MLSError> {
         let mut inner = self.inner.write()
             .map_err(|_| MLSError::ContextNotInitialized)?;
@@ -667,23 +1369,25 @@ impl MLSContext {
         let identity = String::from_utf8(identity_bytes)
             .map_err(|_| MLSError::invalid_input("Invalid UTF-8"))?;
 
-        let credential = Credential::new(
-            CredentialType::Basic,
-            identity.as_bytes().to_vec()
-        );
-        let signature_keys = SignatureKeyPair::new(SignatureScheme::ED25519)
-            .map_err(|_| MLSError::OpenMLSError)?;
+        let credential = MLSContextInner::build_credential(&identity, &credential_type.unwrap_or(CredentialTypeSelector::Basic))?;
+
+        let ciphersuite = crate::mls_context::ciphersuite_for(&ciphersuite.unwrap_or_default());
+        let signature_keys = SignatureKeyPair::new(ciphersuite.signature_algorithm())
+            .map_err(|e| MLSError::openmls_error(e))?;
 
         signature_keys.store(inner.provider().storage())
-            .map_err(|_| MLSError::OpenMLSError)?;
+            .map_err(|e| MLSError::openmls_error(e))?;
 
         // CRITICAL: Register the signer for this identity so it can be found when processing Welcome messages
         let signer_public_key = signature_keys.public().to_vec();
         inner.register_signer(&identity, signer_public_key.clone());
         crate::debug_log!("[MLS-FFI] Registered signer for identity: {}", identity);
 
-        let ciphersuite = Ciphersuite::MLS_128_DHKEMX25519_AES128GCM_SHA256_Ed25519;
-        let key_package_bundle = KeyPackage::builder()
+        let mut builder = KeyPackage::builder();
+        if last_resort {
+            builder = builder.mark_as_last_resort();
+        }
+        let key_package_bundle = builder
             .build(
                 ciphersuite,
                 inner.provider(),
@@ -693,18 +1397,18 @@ impl MLSContext {
                     signature_key: signature_keys.public().into(),
                 },
             )
-            .map_err(|_| MLSError::OpenMLSError)?;
+            .map_err(|e| MLSError::openmls_error(e))?;
 
         // Serialize key package directly (raw format for compatibility)
         let key_package = key_package_bundle.key_package().clone();
 
         let key_package_data = key_package
             .tls_serialize_detached()
-            .map_err(|_| MLSError::SerializationError)?;
+            .map_err(|e| MLSError::serialization_error(e))?;
 
         let hash_ref = key_package
             .hash_ref(inner.provider().crypto())
-            .map_err(|_| MLSError::OpenMLSError)?
+            .map_err(|e| MLSError::openmls_error(e))?
             .as_slice()
             .to_vec();
 
@@ -712,11 +1416,96 @@ impl MLSContext {
         // This ensures the private key material is available when processing Welcome messages
         crate::debug_log!("[MLS-FFI] Storing key package bundle in cache (hash_ref: {})", hex::encode(&hash_ref));
         inner.key_package_bundles.insert(hash_ref.clone(), key_package_bundle);
+        if last_resort {
+            inner.mark_last_resort_bundle(hash_ref.clone());
+            crate::debug_log!("[MLS-FFI] Bundle marked as last-resort, will survive Welcome processing");
+        }
         crate::debug_log!("[MLS-FFI] Bundle cached successfully, cache now has {} bundles", inner.key_package_bundles.len());
 
         Ok(KeyPackageResult { key_package_data, hash_ref })
     }
 
+    /// Mint a fresh KeyPackage for `identity` and retire whichever ones this
+    /// identity previously rotated in, instead of deleting them immediately
+    ///
+    /// Retired bundles stay resolvable (so a Welcome already sent against an
+    /// older published KeyPackage still decrypts) until `retention`'s window
+    /// has elapsed, at which point this same call garbage-collects them. Pass
+    /// `None` for `retention` to use the default (keep at least 3 rotations
+    /// and 7 days).
+    pub fn rotate_key_packages(
+        &self,
+        identity_bytes: Vec<u8>,
+        ciphersuite: Option<CiphersuiteSelector>,
+        credential_type: Option<CredentialTypeSelector>,
+        retention: Option<KeyPackageRetentionPolicy>,
+    ) -> Result<KeyPackageRotationResult, MLSError> {
+        let mut inner = self.inner.write()
+            .map_err(|_| MLSError::ContextNotInitialized)?;
+
+        let identity = String::from_utf8(identity_bytes)
+            .map_err(|_| MLSError::invalid_input("Invalid UTF-8"))?;
+
+        let result = inner.rotate_key_packages(
+            &identity,
+            ciphersuite.unwrap_or_default(),
+            &credential_type.unwrap_or(CredentialTypeSelector::Basic),
+            retention.unwrap_or_default(),
+        )?;
+
+        crate::info_log!("[MLS-FFI] rotate_key_packages: Rotated key package for '{}', {} bundle(s) garbage-collected",
+            identity, result.garbage_collected.len());
+
+        Ok(result)
+    }
+
+    /// Attempt to recover from a `KeyPackageDesyncDetected` failure previously
+    /// raised for `convo_id`
+    ///
+    /// Looks up the desync `process_welcome` recorded for this `convo_id`. If
+    /// a bundle matching the expected key package ref has since appeared
+    /// (e.g. a delayed storage restore raced the Welcome), reports `Recovered`
+    /// with nothing else to do. Otherwise it regenerates and caches a fresh
+    /// `last_resort` key package bundle for the same identity, so retrying the
+    /// Welcome - or any future invite - has a bundle to consume, and reports
+    /// `NeedsReAdd` so the caller knows to publish the new key package and
+    /// have the conversation re-add this client with it.
+    ///
+    /// A desync is only ever detected before the Welcome can be joined, so
+    /// this client was never a member of the group and has no roster to
+    /// check for staleness against - `stale_members` is therefore always
+    /// empty; the caller already knows who it needs to ask for a re-add.
+    /// Returns `Unrecoverable` if nothing was recorded for `convo_id` (e.g. it
+    /// was already healed, or never desynced in the first place).
+    pub fn heal_key_package_desync(&self, convo_id: String) -> Result<HealOutcome, MLSError> {
+        let mut inner = self.inner.write()
+            .map_err(|_| MLSError::ContextNotInitialized)?;
+
+        let Some(desync) = inner.take_key_package_desync(&convo_id) else {
+            crate::debug_log!("[MLS-FFI] heal_key_package_desync: nothing recorded for {}", convo_id);
+            return Ok(HealOutcome::Unrecoverable);
+        };
+
+        if inner.key_package_bundles.contains_key(&desync.expected_ref) {
+            crate::info_log!("[MLS-FFI] heal_key_package_desync: bundle for {} reappeared, nothing to regenerate", convo_id);
+            return Ok(HealOutcome::Recovered);
+        }
+
+        crate::warn_log!("[MLS-FFI] heal_key_package_desync: regenerating key package for identity {} (convo {})", desync.identity, convo_id);
+
+        // No prior bundle to carry provenance forward from here (unlike
+        // `deserialize_storage`'s regeneration pass), so fall back to the
+        // default ciphersuite and a Basic credential, same as this always has
+        inner.regenerate_key_package_bundle(
+            &desync.identity,
+            &CiphersuiteSelector::default(),
+            &CredentialTypeSelector::Basic,
+            true,
+        )?;
+
+        Ok(HealOutcome::NeedsReAdd { stale_members: Vec::new() })
+    }
+
     pub fn process_welcome(
         &self,
         welcome_bytes: Vec<u8>,
@@ -735,7 +1524,7 @@ impl MLSContext {
         let (mls_msg, _) = MlsMessageIn::tls_deserialize_bytes(&welcome_bytes)
             .map_err(|e| {
                 crate::error_log!("[MLS-FFI] ERROR: Failed to deserialize Welcome message: {:?}", e);
-                MLSError::SerializationError
+                MLSError::serialization_error(e)
             })?;
         crate::debug_log!("[MLS-FFI] process_welcome: Welcome message deserialized successfully");
 
@@ -751,19 +1540,40 @@ impl MLSContext {
         let bundle_count = inner.key_package_bundles.len();
         crate::info_log!("[MLS-FFI] process_welcome: Key package bundles in cache: {}", bundle_count);
 
-        if bundle_count == 0 {
-            crate::warn_log!("[MLS-FFI] ‚ö†Ô∏è WARNING: No key package bundles available!");
-            crate::warn_log!("[MLS-FFI]   This indicates potential state desync (app reinstall/database loss)");
+        // The Welcome's GroupInfo (and so its real group ID) is encrypted and
+        // unrecoverable without a matching bundle, but the recipient
+        // KeyPackageRef is sent in the clear - use it as a stable identifier
+        // instead of hashing arbitrary raw bytes
+        let expected_ref = crate::mls_context::MLSContextInner::peek_welcome_key_package_ref(&welcome_bytes)
+            .unwrap_or_default();
+
+        // A desync isn't only "the cache is totally empty" - the realistic
+        // case is this device still has *other* bundles cached but rotated
+        // or GC'd the specific one this Welcome was sent against. Check
+        // containment of the referenced hash_ref whenever it could be read
+        // off the Welcome at all; only fall back to the blanket
+        // empty-cache check if it couldn't be determined.
+        let desynced = if expected_ref.is_empty() {
+            bundle_count == 0
+        } else {
+            !inner.key_package_bundles.contains_key(&expected_ref)
+        };
+
+        if desynced {
+            crate::warn_log!("[MLS-FFI] ‚ö†Ô∏è WARNING: No cached bundle matches this Welcome's key package!");
+            crate::warn_log!("[MLS-FFI]   This indicates potential state desync (app reinstall/database loss, or the bundle was rotated out)");
             crate::warn_log!("[MLS-FFI]   Triggering key package recovery flow...");
 
-            // Try to extract group ID from Welcome for better error reporting
-            // Welcome message secrets are encrypted, but we can try to get basic info
-            let convo_id = format!("welcome_{}", hex::encode(&welcome_bytes[..16.min(welcome_bytes.len())]));
+            let convo_id = if expected_ref.is_empty() {
+                format!("welcome_{}", hex::encode(&welcome_bytes[..16.min(welcome_bytes.len())]))
+            } else {
+                format!("welcome_keypackage_{}", hex::encode(&expected_ref))
+            };
+
+            let found_refs: Vec<Vec<u8>> = inner.key_package_bundles.keys().cloned().collect();
+            inner.record_key_package_desync(convo_id.clone(), identity.clone(), expected_ref.clone());
 
-            return Err(MLSError::key_package_desync_detected(
-                convo_id,
-                "No key package bundles available - likely due to app reinstall or database loss"
-            ));
+            return Err(MLSError::key_package_desync_detected(convo_id, expected_ref, found_refs));
         } else {
             crate::debug_log!("[MLS-FFI] process_welcome: Available bundle hash_refs:");
             for (i, hash_ref) in inner.key_package_bundles.keys().enumerate() {
@@ -782,7 +1592,7 @@ impl MLSContext {
                 group_config.out_of_order_tolerance,
                 group_config.maximum_forward_distance,
             ))
-            .wire_format_policy(PURE_CIPHERTEXT_WIRE_FORMAT_POLICY)
+            .wire_format_policy(crate::mls_context::wire_format_policy_for(&group_config.wire_format_policy))
             .build();
         crate::debug_log!("[MLS-FFI] process_welcome: Join config created");
 
@@ -799,13 +1609,13 @@ impl MLSContext {
             crate::error_log!("[MLS-FFI] ERROR: Error type: {}", std::any::type_name_of_val(&e));
             crate::error_log!("[MLS-FFI] DIAGNOSTIC: This is likely NoMatchingKeyPackage if bundle_count was 0");
             crate::error_log!("[MLS-FFI] DIAGNOSTIC: Check if storage was loaded before calling process_welcome");
-            MLSError::OpenMLSError
+            MLSError::openmls_error(e)
         })?
         .into_group(inner.provider())
         .map_err(|e| {
             crate::error_log!("[MLS-FFI] ‚ùå ERROR: into_group failed!");
             crate::error_log!("[MLS-FFI] ERROR: OpenMLS error details: {:?}", e);
-            MLSError::OpenMLSError
+            MLSError::openmls_error(e)
         })?;
 
         crate::info_log!("[MLS-FFI] process_welcome: Successfully joined group via Welcome");
@@ -827,11 +1637,146 @@ impl MLSContext {
             crate::info_log!("[MLS-FFI] ‚úÖ Exported epoch {} secret after processing Welcome", group.epoch().as_u64());
         }
 
-        inner.add_group(group, &identity)?;
+        inner.add_group(group, &identity, group_config)?;
+        inner.reinstate_last_resort_bundles();
 
         Ok(WelcomeResult { group_id })
     }
 
+    /// Preview a Welcome message before joining the group it invites to
+    ///
+    /// Decrypts the GroupSecrets and ratchet tree far enough to report the
+    /// group id, ciphersuite, epoch, and current member list, without writing
+    /// any group state. Pass the returned `staged_welcome_id` (together with
+    /// the `group_id`) to `join_staged_welcome` to actually join, or simply
+    /// discard it to reject the invitation.
+    pub fn inspect_welcome(&self, welcome_bytes: Vec<u8>, config: Option<GroupConfig>) -> Result<StagedWelcomeInfo, MLSError> {
+        crate::info_log!("[MLS-FFI] inspect_welcome: Previewing {} byte Welcome message", welcome_bytes.len());
+
+        let mut inner = self.inner.write()
+            .map_err(|_| MLSError::ContextNotInitialized)?;
+
+        let preview = inner.inspect_welcome(&welcome_bytes, config.unwrap_or_default())?;
+
+        crate::info_log!("[MLS-FFI] inspect_welcome: Group {} at epoch {} with {} member(s)",
+            hex::encode(&preview.group_id), preview.epoch, preview.member_credentials.len());
+
+        Ok(preview)
+    }
+
+    /// Finish joining a group previously inspected with `inspect_welcome`
+    ///
+    /// `group_id` and `staged_welcome_id` must be the values returned by the
+    /// matching `inspect_welcome` call.
+    pub fn join_staged_welcome(
+        &self,
+        group_id: Vec<u8>,
+        staged_welcome_id: String,
+        identity_bytes: Vec<u8>,
+    ) -> Result<WelcomeResult, MLSError> {
+        crate::info_log!("[MLS-FFI] join_staged_welcome: Joining staged welcome {}", staged_welcome_id);
+
+        let mut inner = self.inner.write()
+            .map_err(|_| MLSError::ContextNotInitialized)?;
+
+        let identity = String::from_utf8(identity_bytes)
+            .map_err(|_| MLSError::invalid_input("Invalid UTF-8"))?;
+
+        let resolved_group_id = inner.join_staged_welcome(&group_id, &staged_welcome_id, &identity)?;
+
+        crate::info_log!("[MLS-FFI] join_staged_welcome: Successfully joined group {}", hex::encode(&resolved_group_id));
+
+        Ok(WelcomeResult { group_id: resolved_group_id })
+    }
+
+    /// Join a group by external commit using a published `GroupInfo`, instead
+    /// of waiting for an existing member to send a Welcome
+    ///
+    /// `group_info_bytes` and `ratchet_tree_bytes` are whatever an existing
+    /// member published alongside an invite link/QR code. Returns the new
+    /// group id and the commit message to broadcast to the group.
+    pub fn join_group_by_external_commit(
+        &self,
+        identity_bytes: Vec<u8>,
+        group_info_bytes: Vec<u8>,
+        ratchet_tree_bytes: Vec<u8>,
+        config: Option<GroupConfig>,
+    ) -> Result<ExternalCommitJoinResult, MLSError> {
+        crate::info_log!("[MLS-FFI] join_group_by_external_commit: Joining via external commit, {} byte GroupInfo", group_info_bytes.len());
+
+        let mut inner = self.inner.write()
+            .map_err(|_| MLSError::ContextNotInitialized)?;
+
+        let identity = String::from_utf8(identity_bytes)
+            .map_err(|_| MLSError::invalid_input("Invalid UTF-8"))?;
+
+        let (group_id, commit_data) = inner.join_group_by_external_commit(&identity, &group_info_bytes, &ratchet_tree_bytes, config.unwrap_or_default())?;
+
+        crate::info_log!("[MLS-FFI] join_group_by_external_commit: Joined group {}", hex::encode(&group_id));
+
+        Ok(ExternalCommitJoinResult { group_id, commit_data })
+    }
+
+    /// Spin off a sub-conversation from `source_group_id` into a brand new
+    /// group, carrying forward that group's resumption PSK and immediately
+    /// adding `key_packages` to it
+    pub fn branch_group(
+        &self,
+        source_group_id: Vec<u8>,
+        identity_bytes: Vec<u8>,
+        key_packages: Vec<KeyPackageData>,
+        config: Option<GroupConfig>,
+    ) -> Result<BranchGroupResult, MLSError> {
+        crate::info_log!("[MLS-FFI] branch_group: Branching from group {} with {} member(s)",
+            hex::encode(&source_group_id), key_packages.len());
+
+        let mut inner = self.inner.write()
+            .map_err(|_| MLSError::ContextNotInitialized)?;
+
+        let identity = String::from_utf8(identity_bytes)
+            .map_err(|_| MLSError::invalid_input("Invalid UTF-8"))?;
+
+        let kps = parse_key_packages(&inner, &key_packages)?;
+
+        let (new_group_id, welcome_data) = inner.branch_group(&source_group_id, &identity, kps, config.unwrap_or_default())?;
+
+        crate::info_log!("[MLS-FFI] branch_group: Created group {}", hex::encode(&new_group_id));
+
+        Ok(BranchGroupResult { new_group_id, welcome_data })
+    }
+
+    /// Replace a wedged group with a freshly created one, carrying forward
+    /// its resumption PSK and re-adding `key_packages` in the same commit
+    /// that creates it
+    pub fn reinit_group(
+        &self,
+        group_id: Vec<u8>,
+        identity_bytes: Vec<u8>,
+        key_packages: Vec<KeyPackageData>,
+        new_config: Option<GroupConfig>,
+    ) -> Result<CommitBundle, MLSError> {
+        crate::info_log!("[MLS-FFI] reinit_group: Reinitializing group {} with {} member(s)",
+            hex::encode(&group_id), key_packages.len());
+
+        let mut inner = self.inner.write()
+            .map_err(|_| MLSError::ContextNotInitialized)?;
+
+        let identity = String::from_utf8(identity_bytes)
+            .map_err(|_| MLSError::invalid_input("Invalid UTF-8"))?;
+
+        let kps = parse_key_packages(&inner, &key_packages)?;
+
+        let (welcome_data, commit_data) = inner.reinit_group(&group_id, &identity, kps, new_config.unwrap_or_default())?;
+
+        crate::info_log!("[MLS-FFI] reinit_group: Replacement group created and {} member(s) re-added", key_packages.len());
+
+        Ok(CommitBundle {
+            commit_data,
+            welcome_data: Some(welcome_data),
+            group_info_data: None,
+        })
+    }
+
     pub fn export_secret(
         &self,
         group_id: Vec<u8>,
@@ -885,14 +1830,14 @@ impl MLSContext {
         // Process commit as a message and extract Update proposals
         let update_proposals = inner.with_group(&gid, |group, provider, _signer| {
             let (mls_msg, _) = MlsMessageIn::tls_deserialize_bytes(&commit_data)
-                .map_err(|_| MLSError::SerializationError)?;
+                .map_err(|e| MLSError::serialization_error(e))?;
 
             let protocol_msg: ProtocolMessage = mls_msg.try_into()
-                .map_err(|_| MLSError::CommitProcessingFailed)?;
+                .map_err(|e| MLSError::commit_processing_failed(e))?;
 
             let processed = group
                 .process_message(provider, protocol_msg)
-                .map_err(|_| MLSError::CommitProcessingFailed)?;
+                .map_err(|e| MLSError::commit_processing_failed(e))?;
 
             match processed.into_content() {
                 ProcessedMessageContent::StagedCommitMessage(staged) => {
@@ -952,6 +1897,89 @@ impl MLSContext {
         })
     }
 
+    /// Stage a received commit and report its full membership/proposal delta
+    ///
+    /// Unlike `process_commit` (which only ever surfaces Update proposals),
+    /// this returns the same `StagedCommitInfo` used by `process_message`'s
+    /// `StagedCommit` branch - adds (with key package refs), removes, extension
+    /// changes, PSKs, and whether the commit self-removes us or carries a
+    /// ReInit - so a caller can validate a commit received out-of-band from
+    /// `process_message` (e.g. replayed from a server queue) before deciding
+    /// whether to `merge_staged_commit` or `reject_staged_commit` it.
+    pub fn stage_commit(
+        &self,
+        group_id: Vec<u8>,
+        commit_data: Vec<u8>,
+    ) -> Result<StagedCommitInfo, MLSError> {
+        let mut inner = self.inner.write()
+            .map_err(|_| MLSError::ContextNotInitialized)?;
+
+        let gid = GroupId::from_slice(&group_id);
+
+        let (mut info, staged, sender, is_external) = inner.with_group(&gid, |group, provider, _signer| {
+            let (mls_msg, _) = MlsMessageIn::tls_deserialize_bytes(&commit_data)
+                .map_err(|e| MLSError::serialization_error(e))?;
+
+            let protocol_msg: ProtocolMessage = mls_msg.try_into()
+                .map_err(|e| MLSError::commit_processing_failed(e))?;
+
+            let processed = group
+                .process_message(provider, protocol_msg)
+                .map_err(|e| MLSError::commit_processing_failed(e))?;
+
+            let sender_credential = processed.credential();
+            let sender = CredentialData {
+                credential_type: format!("{:?}", sender_credential.credential_type()),
+                identity: sender_credential.serialized_content().to_vec(),
+            };
+            let is_external = !matches!(processed.sender(), Sender::Member(_));
+
+            match processed.into_content() {
+                ProcessedMessageContent::StagedCommitMessage(staged) => {
+                    let info = build_staged_commit_info(group, provider, &staged, &group_id, sender.clone(), is_external)?;
+                    Ok((info, staged, sender, is_external))
+                },
+                _ => Err(MLSError::InvalidCommit),
+            }
+        })?;
+
+        // Same one-staged-commit-per-group id scheme `process_message` and
+        // `merge_staged_commit`/`reject_staged_commit` already use
+        let staged_commit_id = hex::encode(&group_id);
+        inner.store_staged_commit(staged_commit_id.clone(), &group_id, info.new_epoch, commit_data, staged, sender, is_external)?;
+        info.staged_commit_id = staged_commit_id;
+
+        Ok(info)
+    }
+
+    /// Re-inspect an already-staged commit's `StagedCommitInfo` without
+    /// consuming it
+    ///
+    /// Unlike `process_message`/`stage_commit` (which each return the delta
+    /// once, at staging time), this re-derives the same summary from the
+    /// commit this context is still holding - useful when the caller's copy
+    /// of that one-time return value was lost (e.g. after a crash) and it
+    /// needs to decide `merge_staged_commit` vs `reject_staged_commit` again
+    /// before either consumes the staged commit.
+    pub fn inspect_staged_commit(&self, group_id: Vec<u8>) -> Result<StagedCommitInfo, MLSError> {
+        let inner = self.inner.read()
+            .map_err(|_| MLSError::ContextNotInitialized)?;
+
+        let gid = GroupId::from_slice(&group_id);
+        let staged_commit_id = hex::encode(&group_id);
+        let stored = inner.peek_staged_commit(&staged_commit_id)
+            .ok_or_else(|| MLSError::invalid_input("No staged commit for this group"))?;
+
+        inner.with_group_ref(&gid, |group, provider| {
+            let mut info = build_staged_commit_info(
+                group, provider, &stored.staged, &group_id,
+                stored.sender_credential.clone(), stored.is_external,
+            )?;
+            info.staged_commit_id = staged_commit_id.clone();
+            Ok(info)
+        })
+    }
+
     /// Clear pending commit for a group
     /// This should be called when a commit is rejected by the delivery service
     /// to clean up pending state in OpenMLS
@@ -963,7 +1991,7 @@ impl MLSContext {
 
         inner.with_group(&gid, |group, provider, _signer| {
             group.clear_pending_commit(provider.storage())
-                .map_err(|_| MLSError::OpenMLSError)?;
+                .map_err(|e| MLSError::openmls_error(e))?;
             Ok(())
         })
     }
@@ -990,6 +2018,15 @@ impl MLSContext {
     }
 
     /// List all pending proposals for a group
+    ///
+    /// `QueuedProposal::proposal_reference()` is `pub(crate)` to OpenMLS, so
+    /// this can't ask an in-memory `QueuedProposal` for its own ref; instead
+    /// it reads back the `hash_ref::ProposalRef`s OpenMLS itself computed (per
+    /// RFC 9420's MakeProposalRef, over the proposal's `AuthenticatedContent`)
+    /// and persisted via `StorageProvider::queue_proposal` when the proposal
+    /// was first processed. Re-serializing those is what makes a ref returned
+    /// here byte-identical to what `remove_proposal` deserializes and looks
+    /// up, so list -> selectively remove -> `commit_pending_proposals` round-trips.
     pub fn list_pending_proposals(
         &self,
         group_id: Vec<u8>,
@@ -1000,27 +2037,27 @@ impl MLSContext {
         let gid = GroupId::from_slice(&group_id);
 
         inner.with_group_ref(&gid, |group, provider| {
-            let proposal_refs: Vec<ProposalRef> = group
-                .pending_proposals()
-                .filter_map(|queued_proposal| {
-                    // Compute proposal reference by hashing the proposal
-                    // Since proposal_reference() is pub(crate), we compute our own identifier
-                    let proposal = queued_proposal.proposal();
-                    let proposal_bytes = proposal
-                        .tls_serialize_detached()
-                        .ok()?;
-
-                    let proposal_ref_bytes = provider.crypto()
-                        .hash(group.ciphersuite().hash_algorithm(), &proposal_bytes)
-                        .ok()?;
+            // The callback-backed `GroupStateStorage` only supports point
+            // lookups, so `queued_proposal_refs` always reports an empty list
+            // for it regardless of what's actually queued - return an
+            // explicit error here instead of letting that read as "nothing
+            // pending" when it really means "can't tell"
+            if provider.is_callback_backend() {
+                return Err(MLSError::unsupported(
+                    "list_pending_proposals is not supported once a GroupStateStorage callback backend is installed - it cannot enumerate queued proposals, only look them up by reference"
+                ));
+            }
 
-                    Some(ProposalRef {
-                        data: proposal_ref_bytes,
-                    })
-                })
-                .collect();
+            let stored_refs = provider.storage()
+                .queued_proposal_refs::<GroupId, openmls::prelude::hash_ref::ProposalRef>(group.group_id())
+                .map_err(|e| MLSError::openmls_error(e))?;
 
-            Ok(proposal_refs)
+            stored_refs
+                .into_iter()
+                .map(|r| r.tls_serialize_detached()
+                    .map(|data| ProposalRef { data })
+                    .map_err(|e| MLSError::serialization_error(e)))
+                .collect::<Result<Vec<ProposalRef>, MLSError>>()
         })
     }
 
@@ -1038,9 +2075,9 @@ impl MLSContext {
         inner.with_group(&gid, |group, provider, _signer| {
             // Remove proposal from the store
             let proposal_reference = openmls::prelude::hash_ref::ProposalRef::tls_deserialize_exact_bytes(&proposal_ref.data)
-                .map_err(|_| MLSError::OpenMLSError)?;
+                .map_err(|e| MLSError::openmls_error(e))?;
             group.remove_pending_proposal(provider.storage(), &proposal_reference)
-                .map_err(|_| MLSError::OpenMLSError)?;
+                .map_err(|e| MLSError::openmls_error(e))?;
             Ok(())
         })
     }
@@ -1059,16 +2096,16 @@ impl MLSContext {
             // Commit all pending proposals
             let (commit_msg, _welcome, _group_info) = group
                 .commit_to_pending_proposals(provider, signer)
-                .map_err(|_| MLSError::OpenMLSError)?;
+                .map_err(|e| MLSError::openmls_error(e))?;
 
             // Merge the pending commit
             group.merge_pending_commit(provider)
-                .map_err(|_| MLSError::OpenMLSError)?;
+                .map_err(|e| MLSError::openmls_error(e))?;
 
             // Serialize the commit
             let commit_data = commit_msg
                 .tls_serialize_detached()
-                .map_err(|_| MLSError::SerializationError)?;
+                .map_err(|e| MLSError::serialization_error(e))?;
 
             Ok(commit_data)
         })
@@ -1085,44 +2122,112 @@ impl MLSContext {
         // CRITICAL: Export epoch secret BEFORE merging commit
         // This allows decrypting messages from the current epoch after the group advances
         let epoch_manager = inner.epoch_secret_manager().clone();
+        let retention_config = inner.config_for_group(&gid)?;
 
-        inner.with_group(&gid, |group, provider, _signer| {
-            // üîç DEBUG: Get member count BEFORE merge
+        let (new_epoch, pre_merge_epoch, resumption_psk) = inner.with_group(&gid, |group, provider, _signer| {
+            // DEBUG: Get member count BEFORE merge
             let member_count_before_merge = group.members().count();
-            crate::debug_log!("[MLS-FFI] üîç DEBUG: Member count BEFORE merge_pending_commit: {}", member_count_before_merge);
+            crate::debug_log!("[MLS-FFI] DEBUG: Member count BEFORE merge_pending_commit: {}", member_count_before_merge);
 
             crate::debug_log!("[MLS-FFI] merge_pending_commit: Exporting current epoch secret before advancing");
 
             // Export current epoch secret before the commit advances the epoch
             if let Err(e) = epoch_manager.export_current_epoch_secret(group, provider) {
-                crate::warn_log!("[MLS-FFI] ‚ö†Ô∏è WARNING: Failed to export epoch secret: {:?}", e);
+                crate::warn_log!("[MLS-FFI] WARNING: Failed to export epoch secret: {:?}", e);
                 crate::debug_log!("[MLS-FFI]   This may cause decryption failures for delayed messages from current epoch");
                 // Continue with merge - epoch secret export is best-effort
             }
 
+            let pre_merge_epoch = group.epoch().as_u64();
+            let resumption_psk = crate::mls_context::export_resumption_psk(group, provider).ok();
+
             group.merge_pending_commit(provider)
-                .map_err(|_| MLSError::MergeFailed)?;
+                .map_err(|e| MLSError::merge_failed(e))?;
 
-            // üîç DEBUG: Get member count AFTER merge
+            // DEBUG: Get member count AFTER merge
             let member_count_after_merge = group.members().count();
-            crate::debug_log!("[MLS-FFI] üîç DEBUG: Member count AFTER merge_pending_commit: {}", member_count_after_merge);
+            crate::debug_log!("[MLS-FFI] DEBUG: Member count AFTER merge_pending_commit: {}", member_count_after_merge);
 
             if member_count_before_merge != member_count_after_merge {
-                crate::warn_log!("[MLS-FFI] ‚ö†Ô∏è WARNING: Member count changed during merge! Before: {}, After: {}",
+                crate::warn_log!("[MLS-FFI] WARNING: Member count changed during merge! Before: {}, After: {}",
                     member_count_before_merge, member_count_after_merge);
             }
 
             let new_epoch = group.epoch().as_u64();
             crate::debug_log!("[MLS-FFI] merge_pending_commit: Advanced to epoch {}", new_epoch);
-            Ok(new_epoch)
-        })
+            Ok((new_epoch, pre_merge_epoch, resumption_psk))
+        })?;
+
+        if let Some(secret) = resumption_psk {
+            inner.capture_resumption_psk(&group_id, pre_merge_epoch, secret);
+        }
+
+        // Reclaim forward secrecy: prune any stored epoch secrets older than
+        // `max_past_epochs` now that the group has advanced
+        if let Err(e) = epoch_manager.enforce_retention(&group_id, new_epoch, &retention_config) {
+            crate::warn_log!("[MLS-FFI] WARNING: Epoch secret retention enforcement failed: {:?}", e);
+        }
+
+        Ok(new_epoch)
     }
 
-    /// Merge a staged commit after validation
-    /// This should be called after validating incoming commits from other members
+    /// Merge a staged commit received via `process_message`, after the caller
+    /// has inspected its `StagedCommitInfo` membership delta and approved it
+    ///
+    /// Unlike `merge_pending_commit` (which merges a commit this client authored
+    /// itself), this merges a `StagedCommit` produced by processing someone
+    /// else's commit message — mirroring OpenMLS's own `merge_staged_commit`
+    /// rather than reusing the self-commit path.
     pub fn merge_staged_commit(&self, group_id: Vec<u8>) -> Result<u64, MLSError> {
-        // OpenMLS uses the same internal method for both pending and staged commits
-        self.merge_pending_commit(group_id)
+        let mut inner = self.inner.write()
+            .map_err(|_| MLSError::ContextNotInitialized)?;
+
+        let gid = GroupId::from_slice(&group_id);
+        let staged_commit_id = hex::encode(&group_id);
+        let stored = inner.take_staged_commit(&staged_commit_id, &group_id)?;
+
+        let epoch_manager = inner.epoch_secret_manager().clone();
+        let retention_config = inner.config_for_group(&gid)?;
+
+        let (new_epoch, pre_merge_epoch, resumption_psk) = inner.with_group(&gid, |group, provider, _signer| {
+            if let Err(e) = epoch_manager.export_current_epoch_secret(group, provider) {
+                crate::warn_log!("[MLS-FFI] WARNING: Failed to export epoch secret before merging staged commit: {:?}", e);
+            }
+
+            let pre_merge_epoch = group.epoch().as_u64();
+            let resumption_psk = crate::mls_context::export_resumption_psk(group, provider).ok();
+
+            group.merge_staged_commit(provider, *stored.staged)
+                .map_err(|e| {
+                    crate::error_log!("[MLS-FFI] ERROR: merge_staged_commit failed: {:?}", e);
+                    MLSError::merge_failed(e)
+                })?;
+
+            Ok((group.epoch().as_u64(), pre_merge_epoch, resumption_psk))
+        })?;
+
+        if let Some(secret) = resumption_psk {
+            inner.capture_resumption_psk(&group_id, pre_merge_epoch, secret);
+        }
+
+        if let Err(e) = epoch_manager.enforce_retention(&group_id, new_epoch, &retention_config) {
+            crate::warn_log!("[MLS-FFI] WARNING: Epoch secret retention enforcement failed: {:?}", e);
+        }
+
+        Ok(new_epoch)
+    }
+
+    /// Discard a staged commit received via `process_message` without merging it
+    ///
+    /// The group stays at its current epoch; use this when the membership
+    /// delta reported in `StagedCommitInfo` should not be accepted.
+    pub fn reject_staged_commit(&self, group_id: Vec<u8>) -> Result<(), MLSError> {
+        let mut inner = self.inner.write()
+            .map_err(|_| MLSError::ContextNotInitialized)?;
+
+        let staged_commit_id = hex::encode(&group_id);
+        inner.take_staged_commit(&staged_commit_id, &group_id)?;
+        Ok(())
     }
 
     /// Check if a group exists in local storage
@@ -1263,27 +2368,38 @@ impl MLSContext {
         Ok(group_id)
     }
 
-    /// Serialize the entire MLS storage for persistence
+    /// Serialize the entire MLS storage for persistence, encrypted under `master_key`
     ///
-    /// Exports all groups, keys, and cryptographic state to a byte blob
-    /// that can be stored in Core Data or Keychain. This should be called
-    /// when the app backgrounds or before termination.
+    /// Exports all groups, keys, and cryptographic state to a JSON blob, then
+    /// seals it in a ChaCha20-Poly1305 AEAD envelope (fresh random nonce per
+    /// call, versioned header) before returning it, so the bytes handed to
+    /// Core Data/Keychain/iCloud backup never carry secrets in plaintext.
+    /// This should be called when the app backgrounds or before termination.
     ///
-    /// - Returns: Serialized storage bytes
-    /// - Throws: MLSError if serialization fails
-    pub fn serialize_storage(&self) -> Result<Vec<u8>, MLSError> {
+    /// This always serializes every group's state; check `dirty_groups` first
+    /// to skip the call entirely when nothing has changed since the last save.
+    ///
+    /// - Parameters:
+    ///   - master_key: 32-byte symmetric key the caller manages (e.g. stored
+    ///     in the Keychain, not included in the blob itself)
+    /// - Returns: Encrypted storage bytes
+    /// - Throws: MLSError if serialization or encryption fails
+    pub fn serialize_storage(&self, master_key: Vec<u8>) -> Result<Vec<u8>, MLSError> {
         crate::debug_log!("[MLS-FFI] serialize_storage: Starting");
 
+        let master_key = storage_master_key(&master_key)?;
+
         let inner = self.inner.read()
             .map_err(|_| MLSError::ContextNotInitialized)?;
 
         let storage_bytes = inner.serialize_storage()?;
+        let sealed = crate::storage_encryption::seal(&master_key, &storage_bytes)?;
 
-        crate::debug_log!("[MLS-FFI] serialize_storage: Complete, {} bytes", storage_bytes.len());
-        Ok(storage_bytes)
+        crate::debug_log!("[MLS-FFI] serialize_storage: Complete, {} bytes sealed", sealed.len());
+        Ok(sealed)
     }
 
-    /// Deserialize and restore MLS storage from persistent bytes
+    /// Deserialize and restore MLS storage from a blob produced by `serialize_storage`
     ///
     /// Restores all groups, keys, and cryptographic state from a previously
     /// serialized storage blob. This should be called during app initialization
@@ -1292,17 +2408,46 @@ impl MLSContext {
     /// WARNING: This replaces the entire storage. Only call during initialization.
     ///
     /// - Parameters:
-    ///   - storage_bytes: Serialized storage from serialize_storage
-    /// - Throws: MLSError if deserialization fails
-    pub fn deserialize_storage(&self, storage_bytes: Vec<u8>) -> Result<(), MLSError> {
-        crate::debug_log!("[MLS-FFI] deserialize_storage: Starting with {} bytes", storage_bytes.len());
+    ///   - master_key: The same 32-byte key `serialize_storage` was called with
+    ///   - storage_bytes: Encrypted storage from serialize_storage
+    /// - Returns: a `BundleRestorationSummary`; if it lists any `regenerated`
+    ///   bundles, publish each one's `key_package_data` so peers stop
+    ///   referencing the hash_ref that went missing
+    /// - Throws: `MLSError::StorageDecryptionFailed` if AEAD tag verification
+    ///   fails (tampered blob or wrong key); other `MLSError`s if the header
+    ///   is malformed or the decrypted JSON can't be parsed
+    pub fn deserialize_storage(&self, master_key: Vec<u8>, storage_bytes: Vec<u8>) -> Result<BundleRestorationSummary, MLSError> {
+        crate::debug_log!("[MLS-FFI] deserialize_storage: Starting with {} sealed bytes", storage_bytes.len());
+
+        let master_key = storage_master_key(&master_key)?;
+        let storage_bytes = crate::storage_encryption::open(&master_key, &storage_bytes)?;
 
         let mut inner = self.inner.write()
             .map_err(|_| MLSError::ContextNotInitialized)?;
 
-        inner.deserialize_storage(&storage_bytes)?;
+        let summary = inner.deserialize_storage(&storage_bytes)?;
+
+        crate::debug_log!("[MLS-FFI] deserialize_storage: Complete, {} regenerated bundle(s)", summary.regenerated.len());
+        Ok(summary)
+    }
 
-        crate::debug_log!("[MLS-FFI] deserialize_storage: Complete");
+    /// List the group ids that have changed since the last `clear_dirty_groups` call
+    ///
+    /// A group is marked dirty when it's created/joined or when any mutating
+    /// operation on it succeeds (sending/processing a message, merging a
+    /// commit, etc.). Check this before calling `serialize_storage` to skip
+    /// the full-blob persist entirely when nothing changed.
+    pub fn dirty_groups(&self) -> Result<Vec<Vec<u8>>, MLSError> {
+        let inner = self.inner.read()
+            .map_err(|_| MLSError::ContextNotInitialized)?;
+        Ok(inner.dirty_group_ids())
+    }
+
+    /// Clear the dirty-group set, typically right after a successful `serialize_storage`
+    pub fn clear_dirty_groups(&self) -> Result<(), MLSError> {
+        let mut inner = self.inner.write()
+            .map_err(|_| MLSError::ContextNotInitialized)?;
+        inner.clear_dirty_groups();
         Ok(())
     }
 
@@ -1324,6 +2469,21 @@ impl MLSContext {
         Ok(count)
     }
 
+    /// List every cached key package bundle's hash_ref and last-resort status
+    ///
+    /// Lets the app replenish regular (single-use) bundles as they're consumed
+    /// while leaving its last-resort fallback bundle in place, rather than
+    /// only seeing an aggregate count from `get_key_package_bundle_count`.
+    ///
+    /// - Returns: One entry per cached bundle, in unspecified order
+    /// - Throws: MLSError if context is not initialized
+    pub fn list_key_package_bundles(&self) -> Result<Vec<KeyPackageBundleInfo>, MLSError> {
+        let inner = self.inner.read()
+            .map_err(|_| MLSError::ContextNotInitialized)?;
+
+        Ok(inner.list_key_package_bundles())
+    }
+
     /// Set the global MLS logger to receive Rust logs in Swift
     ///
     /// This allows forwarding internal MLS logs to OSLog or other Swift logging systems.
@@ -1360,7 +2520,7 @@ impl MLSContext {
                     .map_err(|_| MLSError::InvalidKeyPackage)?;
                 return Ok(kp
                     .hash_ref(provider.crypto())
-                    .map_err(|_| MLSError::OpenMLSError)?
+                    .map_err(|e| MLSError::openmls_error(e))?
                     .as_slice()
                     .to_vec());
             }
@@ -1368,13 +2528,13 @@ impl MLSContext {
         
         // Fallback: raw KeyPackage format
         let (kp_in, _remaining) = KeyPackageIn::tls_deserialize_bytes(&key_package_bytes)
-            .map_err(|_| MLSError::SerializationError)?;
+            .map_err(|e| MLSError::serialization_error(e))?;
         let kp = kp_in
             .validate(provider.crypto(), ProtocolVersion::default())
             .map_err(|_| MLSError::InvalidKeyPackage)?;
         Ok(kp
             .hash_ref(provider.crypto())
-            .map_err(|_| MLSError::OpenMLSError)?
+            .map_err(|e| MLSError::openmls_error(e))?
             .as_slice()
             .to_vec())
     }
@@ -1407,7 +2567,7 @@ pub fn mls_compute_key_package_hash(key_package_bytes: Vec<u8>) -> Result<Vec<u8
                 .map_err(|_| MLSError::InvalidKeyPackage)?;
             return Ok(kp
                 .hash_ref(provider.crypto())
-                .map_err(|_| MLSError::OpenMLSError)?
+                .map_err(|e| MLSError::openmls_error(e))?
                 .as_slice()
                 .to_vec());
         }
@@ -1415,13 +2575,13 @@ pub fn mls_compute_key_package_hash(key_package_bytes: Vec<u8>) -> Result<Vec<u8
     
     // Fallback: raw KeyPackage format
     let (kp_in, _remaining) = KeyPackageIn::tls_deserialize_bytes(&key_package_bytes)
-        .map_err(|_| MLSError::SerializationError)?;
+        .map_err(|e| MLSError::serialization_error(e))?;
     let kp = kp_in
         .validate(provider.crypto(), ProtocolVersion::default())
         .map_err(|_| MLSError::InvalidKeyPackage)?;
     Ok(kp
         .hash_ref(provider.crypto())
-        .map_err(|_| MLSError::OpenMLSError)?
+        .map_err(|e| MLSError::openmls_error(e))?
         .as_slice()
         .to_vec())
 }
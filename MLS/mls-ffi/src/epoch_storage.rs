@@ -7,9 +7,30 @@
 
 use std::sync::{Arc, RwLock};
 use openmls::prelude::*;
-use crate::error::MLSError;
+use crate::error::{MLSError, StorageCallbackError};
 use crate::types::EpochSecretStorage;
 
+/// Map a storage callback outcome into an `MLSError`, preserving whether the
+/// failure is a genuinely missing value (fail cleanly) or a backend/serialization
+/// problem (caller should consider retrying).
+fn map_callback_error(error: StorageCallbackError) -> MLSError {
+    match error {
+        StorageCallbackError::NotFound => MLSError::storage_error("value not found"),
+        StorageCallbackError::Backend { message } => {
+            crate::error_log!("[EPOCH-STORAGE] Storage backend error: {}", message);
+            MLSError::StorageFailed
+        }
+        StorageCallbackError::Serialization { message } => {
+            crate::error_log!("[EPOCH-STORAGE] Serialization error: {}", message);
+            MLSError::StorageFailed
+        }
+        StorageCallbackError::UnexpectedUniFFICallbackError(reason) => {
+            crate::error_log!("[EPOCH-STORAGE] Swift callback panicked: {}", reason);
+            MLSError::StorageFailed
+        }
+    }
+}
+
 /// Epoch secret manager coordinating storage operations
 pub struct EpochSecretManager {
     storage: Arc<RwLock<Option<Arc<dyn EpochSecretStorage>>>>,
@@ -61,16 +82,19 @@ impl EpochSecretManager {
         // Store in Swift encrypted storage
         if let Ok(guard) = self.storage.read() {
             if let Some(storage) = guard.as_ref() {
-                if storage.store_epoch_secret(
+                match storage.store_epoch_secret(
                     group_id_hex.clone(),
                     current_epoch,
                     secret.to_vec(),
                 ) {
-                    crate::info_log!("[EPOCH-STORAGE] ✅ Stored epoch secret: group={}, epoch={}",
-                        group_id_hex, current_epoch);
-                } else {
-                    crate::warn_log!("[EPOCH-STORAGE] ⚠️ Failed to store epoch secret");
-                    return Err(MLSError::StorageFailed);
+                    Ok(()) => {
+                        crate::info_log!("[EPOCH-STORAGE] ✅ Stored epoch secret: group={}, epoch={}",
+                            group_id_hex, current_epoch);
+                    }
+                    Err(e) => {
+                        crate::warn_log!("[EPOCH-STORAGE] ⚠️ Failed to store epoch secret: {:?}", e);
+                        return Err(map_callback_error(e));
+                    }
                 }
             }
         }
@@ -79,20 +103,20 @@ impl EpochSecretManager {
     }
 
     /// Retrieve stored epoch secret
+    ///
+    /// Returns `Err(MLSError::StorageError)` when the epoch secret is genuinely
+    /// missing (decryption should fail cleanly), and `Err(MLSError::StorageFailed)`
+    /// when the backend itself is unavailable (the caller may want to retry).
     pub fn get_epoch_secret(
         &self,
         group_id: &[u8],
         epoch: u64,
-    ) -> Option<Vec<u8>> {
+    ) -> Result<Vec<u8>, MLSError> {
         let group_id_hex = hex::encode(group_id);
 
-        if let Ok(guard) = self.storage.read() {
-            if let Some(storage) = guard.as_ref() {
-                return storage.get_epoch_secret(group_id_hex, epoch);
-            }
-        }
-
-        None
+        let guard = self.storage.read().map_err(|_| MLSError::StorageFailed)?;
+        let storage = guard.as_ref().ok_or(MLSError::StorageFailed)?;
+        storage.get_epoch_secret(group_id_hex, epoch).map_err(map_callback_error)
     }
 
     /// Delete epoch secret (for retention policy cleanup)
@@ -100,16 +124,97 @@ impl EpochSecretManager {
         &self,
         group_id: &[u8],
         epoch: u64,
-    ) -> bool {
+    ) -> Result<(), MLSError> {
         let group_id_hex = hex::encode(group_id);
 
-        if let Ok(guard) = self.storage.read() {
-            if let Some(storage) = guard.as_ref() {
-                return storage.delete_epoch_secret(group_id_hex, epoch);
-            }
+        let guard = self.storage.read().map_err(|_| MLSError::StorageFailed)?;
+        let storage = guard.as_ref().ok_or(MLSError::StorageFailed)?;
+        storage.delete_epoch_secret(group_id_hex, epoch).map_err(map_callback_error)
+    }
+
+    /// Enforce `GroupConfig.max_past_epochs` retention for a group's stored epoch secrets
+    ///
+    /// Enumerates the epochs currently stored for `group_id`, keeps the newest
+    /// `max_past_epochs` of them (plus `current_epoch` itself), and prunes the rest
+    /// in a single batch call so forward secrecy is reclaimed without a per-epoch
+    /// round-trip across the FFI boundary.
+    ///
+    /// This is best-effort: retention failures are logged but never block the
+    /// commit that triggered them.
+    pub fn enforce_retention(
+        &self,
+        group_id: &[u8],
+        current_epoch: u64,
+        config: &crate::types::GroupConfig,
+    ) -> Result<(), MLSError> {
+        let group_id_hex = hex::encode(group_id);
+
+        let guard = self.storage.read().map_err(|_| MLSError::StorageFailed)?;
+        let storage = match guard.as_ref() {
+            Some(storage) => storage,
+            None => return Ok(()), // No storage backend configured yet; nothing to prune
+        };
+
+        let mut stored_epochs = storage
+            .list_epoch_secrets(group_id_hex.clone())
+            .map_err(map_callback_error)?;
+        stored_epochs.sort_unstable();
+
+        let max_past_epochs = config.max_past_epochs as usize;
+        let oldest_retained = current_epoch.saturating_sub(max_past_epochs as u64);
+
+        let to_prune: Vec<u64> = stored_epochs
+            .into_iter()
+            .filter(|&epoch| epoch < oldest_retained)
+            .collect();
+
+        if to_prune.is_empty() {
+            return Ok(());
+        }
+
+        crate::debug_log!("[EPOCH-STORAGE] enforce_retention: pruning {} epoch(s) older than {} for group {}",
+            to_prune.len(), oldest_retained, group_id_hex);
+
+        storage
+            .delete_epoch_secrets(group_id_hex, to_prune)
+            .map_err(map_callback_error)
+    }
+
+    /// Explicitly purge every stored epoch secret strictly older than `before_epoch`
+    ///
+    /// Unlike `enforce_retention` (which runs automatically after every commit and
+    /// keeps a rolling window sized by `GroupConfig.max_past_epochs`), this lets a
+    /// caller forget everything before a specific epoch on demand - e.g. once it
+    /// has confirmed delivery of every message up to that point and no longer
+    /// needs the older secrets for forward secrecy.
+    pub fn prune_epoch_secrets(&self, group_id: &[u8], before_epoch: u64) -> Result<(), MLSError> {
+        let group_id_hex = hex::encode(group_id);
+
+        let guard = self.storage.read().map_err(|_| MLSError::StorageFailed)?;
+        let storage = match guard.as_ref() {
+            Some(storage) => storage,
+            None => return Ok(()),
+        };
+
+        let stored_epochs = storage
+            .list_epoch_secrets(group_id_hex.clone())
+            .map_err(map_callback_error)?;
+
+        let to_prune: Vec<u64> = stored_epochs
+            .into_iter()
+            .filter(|&epoch| epoch < before_epoch)
+            .collect();
+
+        if to_prune.is_empty() {
+            return Ok(());
         }
 
-        false
+        crate::debug_log!("[EPOCH-STORAGE] prune_epoch_secrets: pruning {} epoch(s) older than {} for group {}",
+            to_prune.len(), before_epoch, group_id_hex);
+
+        storage
+            .delete_epoch_secrets(group_id_hex, to_prune)
+            .map_err(map_callback_error)
     }
 }
 
@@ -14,28 +14,43 @@ pub enum MLSError {
     
     #[error("Failed to add members")]
     AddMembersFailed,
-    
+
+    #[error("Failed to remove members")]
+    RemoveMembersFailed,
+
     #[error("Encryption failed")]
     EncryptionFailed,
     
     #[error("Decryption failed")]
     DecryptionFailed,
-    
-    #[error("Serialization error")]
-    SerializationError,
-    
-    #[error("OpenMLS error")]
-    OpenMLSError,
-    
+
+    #[error("No ratcheting secret yet for sender {sender} generation {generation}")]
+    GenerationOutOfBound { sender: String, generation: u32 },
+
+    #[error("AEAD authentication failed while decrypting ciphertext")]
+    AeadError,
+
+    #[error("Ciphertext uses an unexpected wire format")]
+    WrongWireFormat,
+
+    #[error("Decrypted message content was malformed")]
+    MalformedContent,
+
+    #[error("Serialization error: {detail}")]
+    SerializationError { detail: String },
+
+    #[error("OpenMLS error: {detail}")]
+    OpenMLSError { detail: String },
+
     #[error("Invalid group ID")]
     InvalidGroupId,
     
     #[error("Secret export failed")]
     SecretExportFailed,
     
-    #[error("Commit processing failed")]
-    CommitProcessingFailed,
-    
+    #[error("Commit processing failed: {detail}")]
+    CommitProcessingFailed { detail: String },
+
     #[error("Invalid commit")]
     InvalidCommit,
     
@@ -48,23 +63,67 @@ pub enum MLSError {
     #[error("Wire format policy violation: {message}")]
     WireFormatPolicyViolation { message: String },
 
-    #[error("Merge failed")]
-    MergeFailed,
+    #[error("Merge failed: {detail}")]
+    MergeFailed { detail: String },
 
     #[error("No matching key package found: {message}")]
     NoMatchingKeyPackage { message: String },
 
-    #[error("Key package desync detected for conversation {convo_id}: {message}")]
-    KeyPackageDesyncDetected { convo_id: String, message: String },
+    #[error("Key package desync detected for conversation {convo_id}: expected key package {expected_ref:?} not found among {found_refs:?}")]
+    KeyPackageDesyncDetected {
+        convo_id: String,
+        /// hash_ref of the key package the peer expected us to have
+        expected_ref: Vec<u8>,
+        /// hash_refs of the key package bundles we actually have cached, if any
+        found_refs: Vec<Vec<u8>>,
+    },
 
     #[error("Welcome message already consumed or invalid")]
     WelcomeConsumed,
 
-    #[error("Storage error")]
-    StorageError,
+    #[error("Storage error: {detail}")]
+    StorageError { detail: String },
 
     #[error("Storage operation failed")]
     StorageFailed,
+
+    #[error("Encrypted storage blob failed AEAD tag verification - it was tampered with, truncated, or the wrong key was used")]
+    StorageDecryptionFailed,
+
+    #[error("Epoch secret unavailable: {message}")]
+    EpochSecretUnavailable { message: String },
+
+    #[error("Operation not supported by the active storage backend: {message}")]
+    Unsupported { message: String },
+}
+
+/// Error returned by foreign (Swift) storage callback implementations
+///
+/// Distinguishes a genuinely missing value from a backend outage or a
+/// serialization problem, so callers no longer have to infer the cause from
+/// a bare `false`/`None`. Mirrors the `FFICallbackError` pattern used by the
+/// mls-rs UniFFI bindings, including a conversion from UniFFI's own callback
+/// panic/unwind error.
+#[derive(Error, Debug, uniffi::Error)]
+#[uniffi(flat_error)]
+pub enum StorageCallbackError {
+    #[error("Value not found")]
+    NotFound,
+
+    #[error("Storage backend error: {message}")]
+    Backend { message: String },
+
+    #[error("Serialization error: {message}")]
+    Serialization { message: String },
+
+    #[error("Unexpected UniFFI callback error: {0}")]
+    UnexpectedUniFFICallbackError(String),
+}
+
+impl From<uniffi::UnexpectedUniFFICallbackError> for StorageCallbackError {
+    fn from(error: uniffi::UnexpectedUniFFICallbackError) -> Self {
+        Self::UnexpectedUniFFICallbackError(error.reason)
+    }
 }
 
 impl MLSError {
@@ -84,10 +143,74 @@ impl MLSError {
         Self::NoMatchingKeyPackage { message: msg.into() }
     }
 
-    pub fn key_package_desync_detected(convo_id: impl Into<String>, msg: impl Into<String>) -> Self {
+    pub fn key_package_desync_detected(
+        convo_id: impl Into<String>,
+        expected_ref: Vec<u8>,
+        found_refs: Vec<Vec<u8>>,
+    ) -> Self {
         Self::KeyPackageDesyncDetected {
             convo_id: convo_id.into(),
-            message: msg.into(),
+            expected_ref,
+            found_refs,
+        }
+    }
+
+    pub fn epoch_secret_unavailable(msg: impl Into<String>) -> Self {
+        Self::EpochSecretUnavailable { message: msg.into() }
+    }
+
+    pub fn unsupported(msg: impl Into<String>) -> Self {
+        Self::Unsupported { message: msg.into() }
+    }
+
+    pub fn generation_out_of_bound(sender: impl Into<String>, generation: u32) -> Self {
+        Self::GenerationOutOfBound { sender: sender.into(), generation }
+    }
+
+    /// Build a `SerializationError` carrying the underlying `tls_codec`/`serde`
+    /// error's `Debug` output as detail, since most of those error types don't
+    /// implement `Display`
+    pub fn serialization_error(detail: impl std::fmt::Debug) -> Self {
+        Self::SerializationError { detail: format!("{:?}", detail) }
+    }
+
+    /// Build an `OpenMLSError` carrying the underlying OpenMLS error's `Debug`
+    /// output as detail
+    pub fn openmls_error(detail: impl std::fmt::Debug) -> Self {
+        Self::OpenMLSError { detail: format!("{:?}", detail) }
+    }
+
+    /// Build a `CommitProcessingFailed` carrying the underlying error's
+    /// `Debug` output as detail
+    pub fn commit_processing_failed(detail: impl std::fmt::Debug) -> Self {
+        Self::CommitProcessingFailed { detail: format!("{:?}", detail) }
+    }
+
+    /// Build a `MergeFailed` carrying the underlying error's `Debug` output
+    /// as detail
+    pub fn merge_failed(detail: impl std::fmt::Debug) -> Self {
+        Self::MergeFailed { detail: format!("{:?}", detail) }
+    }
+
+    /// Build a `StorageError` carrying the underlying error's `Debug` output
+    /// as detail
+    pub fn storage_error(detail: impl std::fmt::Debug) -> Self {
+        Self::StorageError { detail: format!("{:?}", detail) }
+    }
+
+    /// Prefix additional context onto a detail-carrying error, e.g.
+    /// `.map_err(|e| e.context("merging commit for convo abc123"))`, so a
+    /// single logged error string names both the operation and the root
+    /// cause. A no-op on variants that don't carry a `detail` field.
+    pub fn context(self, msg: impl Into<String>) -> Self {
+        let msg = msg.into();
+        match self {
+            Self::SerializationError { detail } => Self::SerializationError { detail: format!("{}: {}", msg, detail) },
+            Self::OpenMLSError { detail } => Self::OpenMLSError { detail: format!("{}: {}", msg, detail) },
+            Self::CommitProcessingFailed { detail } => Self::CommitProcessingFailed { detail: format!("{}: {}", msg, detail) },
+            Self::MergeFailed { detail } => Self::MergeFailed { detail: format!("{}: {}", msg, detail) },
+            Self::StorageError { detail } => Self::StorageError { detail: format!("{}: {}", msg, detail) },
+            other => other,
         }
     }
 }
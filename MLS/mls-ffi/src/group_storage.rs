@@ -0,0 +1,696 @@
+// group_storage.rs
+//
+// Bridges OpenMLS's `openmls_traits::storage::StorageProvider` to a Swift-implemented
+// `GroupStateStorage` callback, so group state (tree, transcript hash, own leaf nodes,
+// proposal queue, key packages, key pairs) is persisted by the host app instead of
+// living only in OpenMLS's in-memory `MemoryStorage`.
+//
+// The callback is keyed per `(group_id, entity_type, key)` rather than a single
+// opaque per-group state blob, since that's the shape `StorageProvider` itself
+// already demands of us - mirroring it one-to-one means the app's Keychain/SQLite/
+// CoreData store only ever sees values OpenMLS asked it to keep, with no
+// serialization format of our own to keep in sync with OpenMLS's internals.
+
+use std::sync::Arc;
+use openmls_traits::storage::{Entity, Key, StorageProvider, CURRENT_VERSION};
+use openmls_traits::OpenMlsProvider;
+use openmls_rust_crypto::OpenMlsRustCrypto;
+
+use crate::types::{GroupStateStorage, GroupStorageEntityType};
+
+/// Group id used for entities that are not scoped to a single group
+/// (key packages and standalone key pairs live outside any group's tree)
+const UNSCOPED_GROUP_ID: &[u8] = b"__unscoped__";
+
+#[derive(Debug, thiserror::Error)]
+pub enum CallbackStorageError {
+    #[error("Swift callback failed for entity {entity_type:?}: {message}")]
+    CallbackFailed { entity_type: GroupStorageEntityType, message: String },
+    #[error("Serialization error: {0}")]
+    Serialization(String),
+}
+
+/// Adapts a foreign-implemented `GroupStateStorage` callback into OpenMLS's
+/// `StorageProvider` trait so OpenMLS's point reads/writes/deletes are
+/// routed across the FFI boundary to Swift-backed persistence. Listing
+/// (`own_leaf_nodes`, `queued_proposal_refs`) always returns an empty list
+/// and clearing (`clear_own_leaf_nodes`, `clear_proposal_queue`) does
+/// nothing, since the callback only exposes point lookups.
+pub struct CallbackGroupStorage {
+    callback: Arc<dyn GroupStateStorage>,
+}
+
+impl CallbackGroupStorage {
+    pub fn new(callback: Arc<dyn GroupStateStorage>) -> Self {
+        Self { callback }
+    }
+
+    fn put<K: Key<CURRENT_VERSION>, V: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &[u8],
+        entity_type: GroupStorageEntityType,
+        key: &K,
+        value: &V,
+    ) -> Result<(), CallbackStorageError> {
+        let key_bytes = serde_json::to_vec(key)
+            .map_err(|e| CallbackStorageError::Serialization(e.to_string()))?;
+        let value_bytes = serde_json::to_vec(value)
+            .map_err(|e| CallbackStorageError::Serialization(e.to_string()))?;
+
+        self.callback
+            .write(group_id.to_vec(), entity_type, key_bytes, value_bytes)
+            .map_err(|e| CallbackStorageError::CallbackFailed { entity_type, message: e.to_string() })
+    }
+
+    fn get<K: Key<CURRENT_VERSION>, V: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &[u8],
+        entity_type: GroupStorageEntityType,
+        key: &K,
+    ) -> Result<Option<V>, CallbackStorageError> {
+        let key_bytes = serde_json::to_vec(key)
+            .map_err(|e| CallbackStorageError::Serialization(e.to_string()))?;
+
+        match self.callback.read(group_id.to_vec(), entity_type, key_bytes) {
+            Some(bytes) => {
+                let value = serde_json::from_slice(&bytes)
+                    .map_err(|e| CallbackStorageError::Serialization(e.to_string()))?;
+                Ok(Some(value))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn remove<K: Key<CURRENT_VERSION>>(
+        &self,
+        group_id: &[u8],
+        entity_type: GroupStorageEntityType,
+        key: &K,
+    ) -> Result<(), CallbackStorageError> {
+        let key_bytes = serde_json::to_vec(key)
+            .map_err(|e| CallbackStorageError::Serialization(e.to_string()))?;
+        self.callback
+            .delete(group_id.to_vec(), entity_type, key_bytes)
+            .map_err(|e| CallbackStorageError::CallbackFailed { entity_type, message: e.to_string() })
+    }
+}
+
+impl StorageProvider<CURRENT_VERSION> for CallbackGroupStorage {
+    type Error = CallbackStorageError;
+
+    fn write_mls_join_config<GroupId: Key<CURRENT_VERSION>, MlsGroupJoinConfig: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+        config: &MlsGroupJoinConfig,
+    ) -> Result<(), Self::Error> {
+        let gid = serde_json::to_vec(group_id).map_err(|e| CallbackStorageError::Serialization(e.to_string()))?;
+        self.put(&gid, GroupStorageEntityType::GroupConfig, group_id, config)
+    }
+
+    fn mls_group_join_config<GroupId: Key<CURRENT_VERSION>, MlsGroupJoinConfig: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<MlsGroupJoinConfig>, Self::Error> {
+        let gid = serde_json::to_vec(group_id).map_err(|e| CallbackStorageError::Serialization(e.to_string()))?;
+        self.get(&gid, GroupStorageEntityType::GroupConfig, group_id)
+    }
+
+    fn write_tree<GroupId: Key<CURRENT_VERSION>, TreeSync: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+        tree: &TreeSync,
+    ) -> Result<(), Self::Error> {
+        let gid = serde_json::to_vec(group_id).map_err(|e| CallbackStorageError::Serialization(e.to_string()))?;
+        self.put(&gid, GroupStorageEntityType::Tree, group_id, tree)
+    }
+
+    fn tree<GroupId: Key<CURRENT_VERSION>, TreeSync: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<TreeSync>, Self::Error> {
+        let gid = serde_json::to_vec(group_id).map_err(|e| CallbackStorageError::Serialization(e.to_string()))?;
+        self.get(&gid, GroupStorageEntityType::Tree, group_id)
+    }
+
+    fn write_interim_transcript_hash<GroupId: Key<CURRENT_VERSION>, InterimTranscriptHash: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+        hash: &InterimTranscriptHash,
+    ) -> Result<(), Self::Error> {
+        let gid = serde_json::to_vec(group_id).map_err(|e| CallbackStorageError::Serialization(e.to_string()))?;
+        self.put(&gid, GroupStorageEntityType::InterimTranscriptHash, group_id, hash)
+    }
+
+    fn interim_transcript_hash<GroupId: Key<CURRENT_VERSION>, InterimTranscriptHash: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<InterimTranscriptHash>, Self::Error> {
+        let gid = serde_json::to_vec(group_id).map_err(|e| CallbackStorageError::Serialization(e.to_string()))?;
+        self.get(&gid, GroupStorageEntityType::InterimTranscriptHash, group_id)
+    }
+
+    fn write_context<GroupId: Key<CURRENT_VERSION>, GroupContext: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+        context: &GroupContext,
+    ) -> Result<(), Self::Error> {
+        let gid = serde_json::to_vec(group_id).map_err(|e| CallbackStorageError::Serialization(e.to_string()))?;
+        self.put(&gid, GroupStorageEntityType::ContextGroupContext, group_id, context)
+    }
+
+    fn group_context<GroupId: Key<CURRENT_VERSION>, GroupContext: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<GroupContext>, Self::Error> {
+        let gid = serde_json::to_vec(group_id).map_err(|e| CallbackStorageError::Serialization(e.to_string()))?;
+        self.get(&gid, GroupStorageEntityType::ContextGroupContext, group_id)
+    }
+
+    fn write_confirmation_tag<GroupId: Key<CURRENT_VERSION>, ConfirmationTag: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+        tag: &ConfirmationTag,
+    ) -> Result<(), Self::Error> {
+        let gid = serde_json::to_vec(group_id).map_err(|e| CallbackStorageError::Serialization(e.to_string()))?;
+        self.put(&gid, GroupStorageEntityType::ConfirmationTag, group_id, tag)
+    }
+
+    fn confirmation_tag<GroupId: Key<CURRENT_VERSION>, ConfirmationTag: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<ConfirmationTag>, Self::Error> {
+        let gid = serde_json::to_vec(group_id).map_err(|e| CallbackStorageError::Serialization(e.to_string()))?;
+        self.get(&gid, GroupStorageEntityType::ConfirmationTag, group_id)
+    }
+
+    fn append_own_leaf_node<GroupId: Key<CURRENT_VERSION>, LeafNode: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+        leaf_node: &LeafNode,
+    ) -> Result<(), Self::Error> {
+        let gid = serde_json::to_vec(group_id).map_err(|e| CallbackStorageError::Serialization(e.to_string()))?;
+        // Own leaf nodes accumulate across epochs, so key on the serialized leaf node
+        // itself rather than overwriting a single slot
+        self.put(&gid, GroupStorageEntityType::OwnLeafNodes, leaf_node, leaf_node)
+    }
+
+    fn own_leaf_nodes<GroupId: Key<CURRENT_VERSION>, LeafNode: Entity<CURRENT_VERSION>>(
+        &self,
+        _group_id: &GroupId,
+    ) -> Result<Vec<LeafNode>, Self::Error> {
+        // The callback interface exposes point lookups, not listing; the Swift side
+        // is expected to track its own leaf-node key set and re-append as needed.
+        Ok(Vec::new())
+    }
+
+    fn clear_own_leaf_nodes<GroupId: Key<CURRENT_VERSION>>(&self, _group_id: &GroupId) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn queue_proposal<GroupId: Key<CURRENT_VERSION>, ProposalRef: Key<CURRENT_VERSION>, QueuedProposal: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+        proposal_ref: &ProposalRef,
+        proposal: &QueuedProposal,
+    ) -> Result<(), Self::Error> {
+        let gid = serde_json::to_vec(group_id).map_err(|e| CallbackStorageError::Serialization(e.to_string()))?;
+        self.put(&gid, GroupStorageEntityType::ProposalQueue, proposal_ref, proposal)
+    }
+
+    fn queued_proposal<GroupId: Key<CURRENT_VERSION>, ProposalRef: Key<CURRENT_VERSION>, QueuedProposal: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+        proposal_ref: &ProposalRef,
+    ) -> Result<Option<QueuedProposal>, Self::Error> {
+        let gid = serde_json::to_vec(group_id).map_err(|e| CallbackStorageError::Serialization(e.to_string()))?;
+        self.get(&gid, GroupStorageEntityType::ProposalQueue, proposal_ref)
+    }
+
+    fn queued_proposal_refs<GroupId: Key<CURRENT_VERSION>, ProposalRef: Entity<CURRENT_VERSION>>(
+        &self,
+        _group_id: &GroupId,
+    ) -> Result<Vec<ProposalRef>, Self::Error> {
+        // Same limitation as `own_leaf_nodes`: enumeration is not part of the
+        // point-lookup callback surface.
+        Ok(Vec::new())
+    }
+
+    fn remove_proposal<GroupId: Key<CURRENT_VERSION>, ProposalRef: Key<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+        proposal_ref: &ProposalRef,
+    ) -> Result<(), Self::Error> {
+        let gid = serde_json::to_vec(group_id).map_err(|e| CallbackStorageError::Serialization(e.to_string()))?;
+        self.remove(&gid, GroupStorageEntityType::ProposalQueue, proposal_ref)
+    }
+
+    fn clear_proposal_queue<GroupId: Key<CURRENT_VERSION>, ProposalRef: Key<CURRENT_VERSION>>(
+        &self,
+        _group_id: &GroupId,
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn write_key_package<HashReference: Key<CURRENT_VERSION>, KeyPackage: Entity<CURRENT_VERSION>>(
+        &self,
+        hash_ref: &HashReference,
+        key_package: &KeyPackage,
+    ) -> Result<(), Self::Error> {
+        self.put(UNSCOPED_GROUP_ID, GroupStorageEntityType::KeyPackage, hash_ref, key_package)
+    }
+
+    fn key_package<HashReference: Key<CURRENT_VERSION>, KeyPackage: Entity<CURRENT_VERSION>>(
+        &self,
+        hash_ref: &HashReference,
+    ) -> Result<Option<KeyPackage>, Self::Error> {
+        self.get(UNSCOPED_GROUP_ID, GroupStorageEntityType::KeyPackage, hash_ref)
+    }
+
+    fn delete_key_package<HashReference: Key<CURRENT_VERSION>>(&self, hash_ref: &HashReference) -> Result<(), Self::Error> {
+        self.remove(UNSCOPED_GROUP_ID, GroupStorageEntityType::KeyPackage, hash_ref)
+    }
+
+    fn write_psk<PskId: Key<CURRENT_VERSION>, PskBundle: Entity<CURRENT_VERSION>>(
+        &self,
+        psk_id: &PskId,
+        psk: &PskBundle,
+    ) -> Result<(), Self::Error> {
+        self.put(UNSCOPED_GROUP_ID, GroupStorageEntityType::PskBundle, psk_id, psk)
+    }
+
+    fn psk<PskId: Key<CURRENT_VERSION>, PskBundle: Entity<CURRENT_VERSION>>(
+        &self,
+        psk_id: &PskId,
+    ) -> Result<Option<PskBundle>, Self::Error> {
+        self.get(UNSCOPED_GROUP_ID, GroupStorageEntityType::PskBundle, psk_id)
+    }
+
+    fn delete_psk<PskId: Key<CURRENT_VERSION>>(&self, psk_id: &PskId) -> Result<(), Self::Error> {
+        self.remove(UNSCOPED_GROUP_ID, GroupStorageEntityType::PskBundle, psk_id)
+    }
+
+    fn write_encryption_key_pair<EncryptionKey: Key<CURRENT_VERSION>, HpkeKeyPair: Entity<CURRENT_VERSION>>(
+        &self,
+        public_key: &EncryptionKey,
+        key_pair: &HpkeKeyPair,
+    ) -> Result<(), Self::Error> {
+        self.put(UNSCOPED_GROUP_ID, GroupStorageEntityType::EncryptionKeyPair, public_key, key_pair)
+    }
+
+    fn encryption_key_pair<HpkeKeyPair: Entity<CURRENT_VERSION>, EncryptionKey: Key<CURRENT_VERSION>>(
+        &self,
+        public_key: &EncryptionKey,
+    ) -> Result<Option<HpkeKeyPair>, Self::Error> {
+        self.get(UNSCOPED_GROUP_ID, GroupStorageEntityType::EncryptionKeyPair, public_key)
+    }
+
+    fn delete_encryption_key_pair<EncryptionKey: Key<CURRENT_VERSION>>(&self, public_key: &EncryptionKey) -> Result<(), Self::Error> {
+        self.remove(UNSCOPED_GROUP_ID, GroupStorageEntityType::EncryptionKeyPair, public_key)
+    }
+
+    fn write_signature_key_pair<SignaturePublicKey: Key<CURRENT_VERSION>, SignatureKeyPair: Entity<CURRENT_VERSION>>(
+        &self,
+        public_key: &SignaturePublicKey,
+        key_pair: &SignatureKeyPair,
+    ) -> Result<(), Self::Error> {
+        self.put(UNSCOPED_GROUP_ID, GroupStorageEntityType::SignatureKeyPair, public_key, key_pair)
+    }
+
+    fn signature_key_pair<SignatureKeyPair: Entity<CURRENT_VERSION>, SignaturePublicKey: Key<CURRENT_VERSION>>(
+        &self,
+        public_key: &SignaturePublicKey,
+    ) -> Result<Option<SignatureKeyPair>, Self::Error> {
+        self.get(UNSCOPED_GROUP_ID, GroupStorageEntityType::SignatureKeyPair, public_key)
+    }
+
+    fn delete_signature_key_pair<SignaturePublicKey: Key<CURRENT_VERSION>>(&self, public_key: &SignaturePublicKey) -> Result<(), Self::Error> {
+        self.remove(UNSCOPED_GROUP_ID, GroupStorageEntityType::SignatureKeyPair, public_key)
+    }
+
+    fn group_state<GroupId: Key<CURRENT_VERSION>, GroupState: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<GroupState>, Self::Error> {
+        let gid = serde_json::to_vec(group_id).map_err(|e| CallbackStorageError::Serialization(e.to_string()))?;
+        self.get(&gid, GroupStorageEntityType::GroupConfig, group_id)
+    }
+
+    fn write_group_state<GroupId: Key<CURRENT_VERSION>, GroupState: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+        group_state: &GroupState,
+    ) -> Result<(), Self::Error> {
+        let gid = serde_json::to_vec(group_id).map_err(|e| CallbackStorageError::Serialization(e.to_string()))?;
+        self.put(&gid, GroupStorageEntityType::GroupConfig, group_id, group_state)
+    }
+
+    fn delete_group_state<GroupId: Key<CURRENT_VERSION>>(&self, group_id: &GroupId) -> Result<(), Self::Error> {
+        let gid = serde_json::to_vec(group_id).map_err(|e| CallbackStorageError::Serialization(e.to_string()))?;
+        self.remove(&gid, GroupStorageEntityType::GroupConfig, group_id)
+    }
+}
+
+type MemoryStorage = <OpenMlsRustCrypto as OpenMlsProvider>::StorageProvider;
+type MemoryStorageError = <MemoryStorage as StorageProvider<CURRENT_VERSION>>::Error;
+
+/// Error from whichever `StorageBackend` variant is active
+#[derive(Debug, thiserror::Error)]
+pub enum StorageBackendError {
+    #[error("in-memory storage error: {0}")]
+    Memory(MemoryStorageError),
+    #[error("callback storage error: {0}")]
+    Callback(#[from] CallbackStorageError),
+}
+
+/// Which concrete storage OpenMLS reads and writes group state through
+///
+/// `install_storage_provider` swaps a context from the default `Memory` variant
+/// to `Callback`. It does not check whether groups already exist, so whatever
+/// a group wrote before the swap is left behind in the replaced backend.
+pub enum StorageBackend {
+    Memory(MemoryStorage),
+    Callback(CallbackGroupStorage),
+}
+
+impl Default for StorageBackend {
+    fn default() -> Self {
+        Self::Memory(MemoryStorage::default())
+    }
+}
+
+/// Delegates one `StorageProvider` method to whichever `StorageBackend` variant
+/// is active, converting its error into `StorageBackendError`
+macro_rules! delegate_storage {
+    ($self:ident, $method:ident ( $($arg:ident),* )) => {
+        match $self {
+            StorageBackend::Memory(s) => s.$method($($arg),*).map_err(StorageBackendError::Memory),
+            StorageBackend::Callback(s) => s.$method($($arg),*).map_err(StorageBackendError::Callback),
+        }
+    };
+}
+
+impl StorageProvider<CURRENT_VERSION> for StorageBackend {
+    type Error = StorageBackendError;
+
+    fn write_mls_join_config<GroupId: Key<CURRENT_VERSION>, MlsGroupJoinConfig: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+        config: &MlsGroupJoinConfig,
+    ) -> Result<(), Self::Error> {
+        delegate_storage!(self, write_mls_join_config(group_id, config))
+    }
+
+    fn mls_group_join_config<GroupId: Key<CURRENT_VERSION>, MlsGroupJoinConfig: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<MlsGroupJoinConfig>, Self::Error> {
+        delegate_storage!(self, mls_group_join_config(group_id))
+    }
+
+    fn write_tree<GroupId: Key<CURRENT_VERSION>, TreeSync: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+        tree: &TreeSync,
+    ) -> Result<(), Self::Error> {
+        delegate_storage!(self, write_tree(group_id, tree))
+    }
+
+    fn tree<GroupId: Key<CURRENT_VERSION>, TreeSync: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<TreeSync>, Self::Error> {
+        delegate_storage!(self, tree(group_id))
+    }
+
+    fn write_interim_transcript_hash<GroupId: Key<CURRENT_VERSION>, InterimTranscriptHash: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+        hash: &InterimTranscriptHash,
+    ) -> Result<(), Self::Error> {
+        delegate_storage!(self, write_interim_transcript_hash(group_id, hash))
+    }
+
+    fn interim_transcript_hash<GroupId: Key<CURRENT_VERSION>, InterimTranscriptHash: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<InterimTranscriptHash>, Self::Error> {
+        delegate_storage!(self, interim_transcript_hash(group_id))
+    }
+
+    fn write_context<GroupId: Key<CURRENT_VERSION>, GroupContext: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+        context: &GroupContext,
+    ) -> Result<(), Self::Error> {
+        delegate_storage!(self, write_context(group_id, context))
+    }
+
+    fn group_context<GroupId: Key<CURRENT_VERSION>, GroupContext: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<GroupContext>, Self::Error> {
+        delegate_storage!(self, group_context(group_id))
+    }
+
+    fn write_confirmation_tag<GroupId: Key<CURRENT_VERSION>, ConfirmationTag: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+        tag: &ConfirmationTag,
+    ) -> Result<(), Self::Error> {
+        delegate_storage!(self, write_confirmation_tag(group_id, tag))
+    }
+
+    fn confirmation_tag<GroupId: Key<CURRENT_VERSION>, ConfirmationTag: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<ConfirmationTag>, Self::Error> {
+        delegate_storage!(self, confirmation_tag(group_id))
+    }
+
+    fn append_own_leaf_node<GroupId: Key<CURRENT_VERSION>, LeafNode: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+        leaf_node: &LeafNode,
+    ) -> Result<(), Self::Error> {
+        delegate_storage!(self, append_own_leaf_node(group_id, leaf_node))
+    }
+
+    fn own_leaf_nodes<GroupId: Key<CURRENT_VERSION>, LeafNode: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Vec<LeafNode>, Self::Error> {
+        delegate_storage!(self, own_leaf_nodes(group_id))
+    }
+
+    fn clear_own_leaf_nodes<GroupId: Key<CURRENT_VERSION>>(&self, group_id: &GroupId) -> Result<(), Self::Error> {
+        delegate_storage!(self, clear_own_leaf_nodes(group_id))
+    }
+
+    fn queue_proposal<GroupId: Key<CURRENT_VERSION>, ProposalRef: Key<CURRENT_VERSION>, QueuedProposal: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+        proposal_ref: &ProposalRef,
+        proposal: &QueuedProposal,
+    ) -> Result<(), Self::Error> {
+        delegate_storage!(self, queue_proposal(group_id, proposal_ref, proposal))
+    }
+
+    fn queued_proposal<GroupId: Key<CURRENT_VERSION>, ProposalRef: Key<CURRENT_VERSION>, QueuedProposal: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+        proposal_ref: &ProposalRef,
+    ) -> Result<Option<QueuedProposal>, Self::Error> {
+        delegate_storage!(self, queued_proposal(group_id, proposal_ref))
+    }
+
+    fn queued_proposal_refs<GroupId: Key<CURRENT_VERSION>, ProposalRef: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Vec<ProposalRef>, Self::Error> {
+        delegate_storage!(self, queued_proposal_refs(group_id))
+    }
+
+    fn remove_proposal<GroupId: Key<CURRENT_VERSION>, ProposalRef: Key<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+        proposal_ref: &ProposalRef,
+    ) -> Result<(), Self::Error> {
+        delegate_storage!(self, remove_proposal(group_id, proposal_ref))
+    }
+
+    fn clear_proposal_queue<GroupId: Key<CURRENT_VERSION>, ProposalRef: Key<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<(), Self::Error> {
+        match self {
+            StorageBackend::Memory(s) => s.clear_proposal_queue::<GroupId, ProposalRef>(group_id).map_err(StorageBackendError::Memory),
+            StorageBackend::Callback(s) => s.clear_proposal_queue::<GroupId, ProposalRef>(group_id).map_err(StorageBackendError::Callback),
+        }
+    }
+
+    fn write_key_package<HashReference: Key<CURRENT_VERSION>, KeyPackage: Entity<CURRENT_VERSION>>(
+        &self,
+        hash_ref: &HashReference,
+        key_package: &KeyPackage,
+    ) -> Result<(), Self::Error> {
+        delegate_storage!(self, write_key_package(hash_ref, key_package))
+    }
+
+    fn key_package<HashReference: Key<CURRENT_VERSION>, KeyPackage: Entity<CURRENT_VERSION>>(
+        &self,
+        hash_ref: &HashReference,
+    ) -> Result<Option<KeyPackage>, Self::Error> {
+        delegate_storage!(self, key_package(hash_ref))
+    }
+
+    fn delete_key_package<HashReference: Key<CURRENT_VERSION>>(&self, hash_ref: &HashReference) -> Result<(), Self::Error> {
+        delegate_storage!(self, delete_key_package(hash_ref))
+    }
+
+    fn write_psk<PskId: Key<CURRENT_VERSION>, PskBundle: Entity<CURRENT_VERSION>>(
+        &self,
+        psk_id: &PskId,
+        psk: &PskBundle,
+    ) -> Result<(), Self::Error> {
+        delegate_storage!(self, write_psk(psk_id, psk))
+    }
+
+    fn psk<PskId: Key<CURRENT_VERSION>, PskBundle: Entity<CURRENT_VERSION>>(
+        &self,
+        psk_id: &PskId,
+    ) -> Result<Option<PskBundle>, Self::Error> {
+        delegate_storage!(self, psk(psk_id))
+    }
+
+    fn delete_psk<PskId: Key<CURRENT_VERSION>>(&self, psk_id: &PskId) -> Result<(), Self::Error> {
+        delegate_storage!(self, delete_psk(psk_id))
+    }
+
+    fn write_encryption_key_pair<EncryptionKey: Key<CURRENT_VERSION>, HpkeKeyPair: Entity<CURRENT_VERSION>>(
+        &self,
+        public_key: &EncryptionKey,
+        key_pair: &HpkeKeyPair,
+    ) -> Result<(), Self::Error> {
+        delegate_storage!(self, write_encryption_key_pair(public_key, key_pair))
+    }
+
+    fn encryption_key_pair<HpkeKeyPair: Entity<CURRENT_VERSION>, EncryptionKey: Key<CURRENT_VERSION>>(
+        &self,
+        public_key: &EncryptionKey,
+    ) -> Result<Option<HpkeKeyPair>, Self::Error> {
+        delegate_storage!(self, encryption_key_pair(public_key))
+    }
+
+    fn delete_encryption_key_pair<EncryptionKey: Key<CURRENT_VERSION>>(&self, public_key: &EncryptionKey) -> Result<(), Self::Error> {
+        delegate_storage!(self, delete_encryption_key_pair(public_key))
+    }
+
+    fn write_signature_key_pair<SignaturePublicKey: Key<CURRENT_VERSION>, SignatureKeyPair: Entity<CURRENT_VERSION>>(
+        &self,
+        public_key: &SignaturePublicKey,
+        key_pair: &SignatureKeyPair,
+    ) -> Result<(), Self::Error> {
+        delegate_storage!(self, write_signature_key_pair(public_key, key_pair))
+    }
+
+    fn signature_key_pair<SignatureKeyPair: Entity<CURRENT_VERSION>, SignaturePublicKey: Key<CURRENT_VERSION>>(
+        &self,
+        public_key: &SignaturePublicKey,
+    ) -> Result<Option<SignatureKeyPair>, Self::Error> {
+        delegate_storage!(self, signature_key_pair(public_key))
+    }
+
+    fn delete_signature_key_pair<SignaturePublicKey: Key<CURRENT_VERSION>>(&self, public_key: &SignaturePublicKey) -> Result<(), Self::Error> {
+        delegate_storage!(self, delete_signature_key_pair(public_key))
+    }
+
+    fn group_state<GroupId: Key<CURRENT_VERSION>, GroupState: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<GroupState>, Self::Error> {
+        delegate_storage!(self, group_state(group_id))
+    }
+
+    fn write_group_state<GroupId: Key<CURRENT_VERSION>, GroupState: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+        group_state: &GroupState,
+    ) -> Result<(), Self::Error> {
+        delegate_storage!(self, write_group_state(group_id, group_state))
+    }
+
+    fn delete_group_state<GroupId: Key<CURRENT_VERSION>>(&self, group_id: &GroupId) -> Result<(), Self::Error> {
+        delegate_storage!(self, delete_group_state(group_id))
+    }
+}
+
+/// The `OpenMlsProvider` actually installed in a running `MLSContextInner`
+///
+/// Always uses `OpenMlsRustCrypto`'s crypto/rand implementations; only the
+/// storage half is swappable, via `StorageBackend`. This is what
+/// `install_storage_provider` switches from `Memory` to `Callback`.
+pub struct ContextProvider {
+    crypto: OpenMlsRustCrypto,
+    storage: StorageBackend,
+}
+
+impl Default for ContextProvider {
+    fn default() -> Self {
+        Self {
+            crypto: OpenMlsRustCrypto::default(),
+            storage: StorageBackend::default(),
+        }
+    }
+}
+
+impl ContextProvider {
+    /// Switch to a `GroupStateStorage` callback-backed storage, in place of
+    /// the default in-memory `MemoryStorage`
+    ///
+    /// Intended to be called once, before any group operation runs; swapping
+    /// storage out from under a live group would strand whatever it already
+    /// wrote to the previous backend.
+    pub fn install_callback_storage(&mut self, callback: Arc<dyn GroupStateStorage>) {
+        self.storage = StorageBackend::Callback(CallbackGroupStorage::new(callback));
+    }
+
+    /// The in-memory `MemoryStorage` backing this provider, if that's the
+    /// active backend; used by `serialize_storage`/`deserialize_storage`,
+    /// which only make sense for the all-or-nothing in-memory case
+    pub fn memory_storage(&self) -> Option<&MemoryStorage> {
+        match &self.storage {
+            StorageBackend::Memory(m) => Some(m),
+            StorageBackend::Callback(_) => None,
+        }
+    }
+
+    /// True once `install_callback_storage` has switched this provider over to
+    /// a `GroupStateStorage` callback backend
+    ///
+    /// `CallbackGroupStorage::queued_proposal_refs`/`own_leaf_nodes` can't
+    /// actually enumerate anything (the callback interface only exposes point
+    /// lookups) and always return an empty list, so callers that need to tell
+    /// "nothing queued" apart from "can't tell" - like `list_pending_proposals`
+    /// - check this first rather than trusting an empty result.
+    pub fn is_callback_backend(&self) -> bool {
+        matches!(self.storage, StorageBackend::Callback(_))
+    }
+}
+
+impl OpenMlsProvider for ContextProvider {
+    type CryptoProvider = <OpenMlsRustCrypto as OpenMlsProvider>::CryptoProvider;
+    type RandProvider = <OpenMlsRustCrypto as OpenMlsProvider>::RandProvider;
+    type StorageProvider = StorageBackend;
+
+    fn storage(&self) -> &Self::StorageProvider {
+        &self.storage
+    }
+
+    fn crypto(&self) -> &Self::CryptoProvider {
+        self.crypto.crypto()
+    }
+
+    fn rand(&self) -> &Self::RandProvider {
+        self.crypto.rand()
+    }
+}
@@ -3,6 +3,10 @@ mod mls_context;
 mod types;
 mod api;
 mod epoch_storage;
+mod group_storage;
+mod staged_registry;
+mod storage_encryption;
+mod resumption_psk;
 pub mod logging;
 
 pub use api::*;
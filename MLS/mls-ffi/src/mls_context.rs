@@ -2,10 +2,10 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use openmls::prelude::*;
 use openmls::ciphersuite::hash_ref::HashReference;
-use openmls::group::PURE_CIPHERTEXT_WIRE_FORMAT_POLICY;
+use openmls::group::{PURE_CIPHERTEXT_WIRE_FORMAT_POLICY, PURE_PLAINTEXT_WIRE_FORMAT_POLICY, MIXED_PLAINTEXT_WIRE_FORMAT_POLICY};
 use openmls_basic_credential::SignatureKeyPair;
-use openmls_rust_crypto::OpenMlsRustCrypto;
 use openmls_traits::storage::StorageProvider;
+use openmls::prelude::tls_codec::Serialize as TlsSerialize;
 use serde::{Serialize, Deserialize};
 
 use crate::error::MLSError;
@@ -16,6 +16,125 @@ use crate::epoch_storage::EpochSecretManager;
 struct GroupMetadata {
     group_id: Vec<u8>,
     signer_public_key: Vec<u8>,
+    max_past_epochs: u32,
+    out_of_order_tolerance: u32,
+    maximum_forward_distance: u32,
+    ciphersuite: crate::types::CiphersuiteSelector,
+    credential_type: crate::types::CredentialTypeSelector,
+    wire_format_policy: crate::types::WireFormatPolicySelector,
+}
+
+/// Map a `CiphersuiteSelector` to the matching OpenMLS `Ciphersuite` constant
+pub(crate) fn ciphersuite_for(selector: &crate::types::CiphersuiteSelector) -> Ciphersuite {
+    use crate::types::CiphersuiteSelector::*;
+    match selector {
+        X25519Aes128Sha256Ed25519 => Ciphersuite::MLS_128_DHKEMX25519_AES128GCM_SHA256_Ed25519,
+        P256Aes128Sha256P256 => Ciphersuite::MLS_128_DHKEMP256_AES128GCM_SHA256_P256,
+        X25519Chacha20Sha256Ed25519 => Ciphersuite::MLS_128_DHKEMX25519_CHACHA20POLY1305_SHA256_Ed25519,
+        X448Aes256Sha512Ed448 => Ciphersuite::MLS_256_DHKEMX448_AES256GCM_SHA512_Ed448,
+        P521Aes256Sha512P521 => Ciphersuite::MLS_256_DHKEMP521_AES256GCM_SHA512_P521,
+    }
+}
+
+/// Map an OpenMLS `Ciphersuite` back to its `CiphersuiteSelector`, for code
+/// that needs to recover which selector a live `KeyPackage`/group was built
+/// with (e.g. `deserialize_storage`'s bundle-regeneration path) rather than
+/// assuming the default. `None` for a suite this crate never builds with
+/// itself (e.g. one only ever seen on an incoming message from a peer).
+pub(crate) fn ciphersuite_selector_for(ciphersuite: Ciphersuite) -> Option<crate::types::CiphersuiteSelector> {
+    use crate::types::CiphersuiteSelector::*;
+    match ciphersuite {
+        Ciphersuite::MLS_128_DHKEMX25519_AES128GCM_SHA256_Ed25519 => Some(X25519Aes128Sha256Ed25519),
+        Ciphersuite::MLS_128_DHKEMP256_AES128GCM_SHA256_P256 => Some(P256Aes128Sha256P256),
+        Ciphersuite::MLS_128_DHKEMX25519_CHACHA20POLY1305_SHA256_Ed25519 => Some(X25519Chacha20Sha256Ed25519),
+        Ciphersuite::MLS_256_DHKEMX448_AES256GCM_SHA512_Ed448 => Some(X448Aes256Sha512Ed448),
+        Ciphersuite::MLS_256_DHKEMP521_AES256GCM_SHA512_P521 => Some(P521Aes256Sha512P521),
+        _ => None,
+    }
+}
+
+/// Export a resumption PSK for `group` at its current epoch, for the caller
+/// to capture into a `ResumptionPskStore` alongside the matching
+/// `export_current_epoch_secret` call. Uses the same exporter-secret
+/// mechanism `EpochSecretManager` already relies on, under a distinct label,
+/// rather than OpenMLS's own internal resumption-PSK bookkeeping (which
+/// isn't exposed for injecting into a group this crate didn't create).
+pub(crate) fn export_resumption_psk(group: &MlsGroup, provider: &impl OpenMlsProvider) -> Result<Vec<u8>, MLSError> {
+    let group_id_hex = hex::encode(group.group_id().as_slice());
+    group
+        .export_secret(provider, "resumption_psk", group_id_hex.as_bytes(), 32)
+        .map(|secret| secret.to_vec())
+        .map_err(|_| MLSError::SecretExportFailed)
+}
+
+/// Queue a Member-Add proposal per entry in `members`, plus (if a resumption
+/// PSK was captured for the group this one is branching/reiniting from) an
+/// external-PSK proposal binding `resumption_psk` into the same commit, then
+/// commit everything queued and merge it immediately
+///
+/// This is what actually carries trust forward from the source group:
+/// `branch_group`/`reinit_group` used to just copy the captured secret into
+/// the new group's `ResumptionPskStore` entry without ever proposing it, so
+/// the new group's key schedule never incorporated it. Mint a
+/// `PreSharedKeyId` for the secret, persist it via OpenMLS's own PSK
+/// key-store write path, then propose + commit it the same way any other
+/// proposal is queued and committed.
+fn commit_with_resumption_psk(
+    group: &mut MlsGroup,
+    provider: &crate::group_storage::ContextProvider,
+    signer: &SignatureKeyPair,
+    members: &[KeyPackage],
+    resumption_psk: Option<&[u8]>,
+) -> Result<(MlsMessageOut, Option<MlsMessageOut>), MLSError> {
+    for key_package in members {
+        group
+            .propose_add_member(provider, signer, key_package)
+            .map_err(|e| {
+                crate::error_log!("[MLS-CONTEXT] commit_with_resumption_psk: failed to propose add member: {:?}", e);
+                MLSError::AddMembersFailed
+            })?;
+    }
+
+    if let Some(secret) = resumption_psk {
+        let ciphersuite = group.ciphersuite();
+        let psk_id = PreSharedKeyId::new(
+            ciphersuite,
+            provider.rand(),
+            Psk::External(ExternalPsk::new(group.group_id().as_slice().to_vec())),
+        )
+        .map_err(|e| MLSError::openmls_error(e))?;
+
+        psk_id
+            .write_to_key_store(provider.storage(), ciphersuite, secret)
+            .map_err(|e| MLSError::openmls_error(e))?;
+
+        group
+            .propose_external_psk(provider, signer, psk_id)
+            .map_err(|e| {
+                crate::error_log!("[MLS-CONTEXT] commit_with_resumption_psk: failed to propose resumption PSK: {:?}", e);
+                MLSError::openmls_error(e)
+            })?;
+    }
+
+    let (commit, welcome, _group_info) = group
+        .commit_to_pending_proposals(provider, signer)
+        .map_err(|e| {
+            crate::error_log!("[MLS-CONTEXT] commit_with_resumption_psk: commit_to_pending_proposals failed: {:?}", e);
+            MLSError::openmls_error(e)
+        })?;
+
+    group.merge_pending_commit(provider).map_err(|e| MLSError::merge_failed(e))?;
+
+    Ok((commit, welcome))
+}
+
+/// Map a `WireFormatPolicySelector` to the matching OpenMLS standard policy constant
+pub(crate) fn wire_format_policy_for(selector: &crate::types::WireFormatPolicySelector) -> WireFormatPolicy {
+    match selector {
+        crate::types::WireFormatPolicySelector::PureCiphertext => PURE_CIPHERTEXT_WIRE_FORMAT_POLICY,
+        crate::types::WireFormatPolicySelector::PurePlaintext => PURE_PLAINTEXT_WIRE_FORMAT_POLICY,
+        crate::types::WireFormatPolicySelector::Mixed => MIXED_PLAINTEXT_WIRE_FORMAT_POLICY,
+    }
 }
 
 /// Serializable key package bundle (hash_ref and serialized bundle)
@@ -23,6 +142,38 @@ struct GroupMetadata {
 struct SerializedKeyPackageBundle {
     hash_ref: Vec<u8>,
     bundle_bytes: Vec<u8>,
+    /// Content hash of the bundle's TLS-serialized key package at the time it
+    /// was written, so `deserialize_storage` can tell a storage backend that
+    /// silently returned the wrong (or truncated) bytes for a hash_ref apart
+    /// from one that genuinely has nothing for it. Defaulted so blobs written
+    /// before this field existed restore without a manifest to check against
+    #[serde(default)]
+    content_hash: Vec<u8>,
+    /// The identity, ciphersuite, and credential type this bundle was built
+    /// with, so a bundle that turns out missing at restore time can be
+    /// regenerated with the same shape instead of a hardcoded default.
+    /// Defaulted (empty identity) for blobs written before this existed -
+    /// `deserialize_storage` treats an empty identity as "can't recover, skip"
+    /// rather than guessing.
+    #[serde(default)]
+    identity: String,
+    #[serde(default)]
+    ciphersuite: crate::types::CiphersuiteSelector,
+    #[serde(default)]
+    credential_type: crate::types::CredentialTypeSelector,
+}
+
+/// One rotation's worth of history for a `rotate_key_packages` call, tracked
+/// so a retired bundle stays resolvable (for an in-flight Welcome that still
+/// references its hash_ref) until `garbage_collect_retired_key_packages`
+/// decides its retention window has elapsed
+#[derive(Serialize, Deserialize, Clone)]
+struct KeyPackageHistoryEntry {
+    identity: String,
+    hash_ref: Vec<u8>,
+    created_at_unix_secs: u64,
+    rotation_index: u64,
+    retired: bool,
 }
 
 /// Complete serialized state including storage and group metadata
@@ -32,59 +183,541 @@ struct SerializedState {
     group_metadata: Vec<GroupMetadata>,
     signers_by_identity: Vec<(String, String)>, // hex-encoded key-value pairs
     key_package_bundles: Vec<SerializedKeyPackageBundle>, // CRITICAL: Must persist bundles for Welcome processing
+    /// Rotation history for `rotate_key_packages`; defaulted so blobs written
+    /// before this field existed still deserialize
+    #[serde(default)]
+    key_package_history: Vec<KeyPackageHistoryEntry>,
+    #[serde(default)]
+    rotation_counter: u64,
+    /// hash_refs of last-resort bundles, tracked in their own list
+    /// independent of `key_package_bundles` so `deserialize_storage` can
+    /// restore them from provider storage on a dedicated pass even when that
+    /// list is empty - the scenario this exists for is a device that's
+    /// burned through every regular bundle and would otherwise hit "No
+    /// bundles to restore"
+    #[serde(default)]
+    last_resort_hash_refs: Vec<Vec<u8>>,
+    /// Schema version this blob was written at; defaults to 0 for blobs
+    /// written before this field existed (everything up to and including
+    /// the `last_resort_hash_refs` addition above). See `migrate_serialized_state`
+    #[serde(default)]
+    schema_version: u32,
+}
+
+/// Current time as Unix seconds, for stamping `KeyPackageHistoryEntry::created_at_unix_secs`
+fn now_unix_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// The schema version `serialize_storage` stamps on every blob it writes.
+/// Bump this and add a step to `migrate_serialized_state` whenever a change
+/// to `SerializedState` needs more than a `#[serde(default)]` to read old data
+const CURRENT_SCHEMA_VERSION: u32 = 4;
+
+/// Bring a raw `serialized_state` JSON blob from `from_version` up to
+/// `CURRENT_SCHEMA_VERSION`, one version at a time, before it's handed to
+/// `serde_json::from_value::<SerializedState>`.
+///
+/// Most of the fields added so far already tolerate a missing blob via
+/// `#[serde(default)]`, so the steps below are no-ops in practice today -
+/// they exist to document what each version added and to give a future
+/// migration that *does* need to restructure data (rename a field, split one
+/// field into two) a version boundary to hook into rather than overloading
+/// `#[serde(default)]` for something it can't express. Unknown newer
+/// versions are rejected rather than silently read, since guessing at a
+/// shape this build has never seen risks dropping fields it doesn't know to
+/// look for.
+fn migrate_serialized_state(value: serde_json::Value, from_version: u32) -> Result<serde_json::Value, MLSError> {
+    if from_version > CURRENT_SCHEMA_VERSION {
+        return Err(MLSError::invalid_input(format!(
+            "serialized_state schema_version {} is newer than this build supports (max {}); refusing to guess at its shape",
+            from_version, CURRENT_SCHEMA_VERSION
+        )));
+    }
+
+    let mut value = value;
+    let mut version = from_version;
+
+    if version < 1 {
+        // v0 -> v1: added `key_package_history` / `rotation_counter` (`rotate_key_packages`)
+        if let Some(obj) = value.as_object_mut() {
+            obj.entry("key_package_history").or_insert_with(|| serde_json::json!([]));
+            obj.entry("rotation_counter").or_insert_with(|| serde_json::json!(0));
+        }
+        version = 1;
+    }
+
+    if version < 2 {
+        // v1 -> v2: added `last_resort_hash_refs` (persisted last-resort bundle tracking)
+        if let Some(obj) = value.as_object_mut() {
+            obj.entry("last_resort_hash_refs").or_insert_with(|| serde_json::json!([]));
+        }
+        version = 2;
+    }
+
+    if version < 3 {
+        // v2 -> v3: introduced `schema_version` itself; no other field changes
+        version = 3;
+    }
+
+    if version < 4 {
+        // v3 -> v4: each entry in `key_package_bundles` gained a `content_hash`
+        // manifest entry; bundles written before this version have nothing to
+        // verify against, so `deserialize_storage` treats an empty hash as
+        // "unknown, don't flag as corrupt" rather than a mismatch
+        if let Some(bundles) = value.get_mut("key_package_bundles").and_then(|b| b.as_array_mut()) {
+            for bundle in bundles {
+                if let Some(obj) = bundle.as_object_mut() {
+                    obj.entry("content_hash").or_insert_with(|| serde_json::json!([]));
+                }
+            }
+        }
+        version = 4;
+    }
+
+    if let Some(obj) = value.as_object_mut() {
+        obj.insert("schema_version".to_string(), serde_json::json!(version));
+    }
+
+    Ok(value)
+}
+
+/// Content hash recorded in a bundle's integrity-manifest entry: the bundle's
+/// key package, TLS-serialized and hashed with its own ciphersuite's hash
+/// algorithm - the same hash primitive `process_message` already uses for
+/// proposal references, rather than pulling in a separate hashing dependency
+fn content_hash_for_bundle(
+    provider: &crate::group_storage::ContextProvider,
+    bundle: &KeyPackageBundle,
+) -> Result<Vec<u8>, MLSError> {
+    let key_package_bytes = bundle
+        .key_package()
+        .tls_serialize_detached()
+        .map_err(|e| MLSError::serialization_error(e))?;
+
+    provider
+        .crypto()
+        .hash(bundle.key_package().ciphersuite().hash_algorithm(), &key_package_bytes)
+        .map_err(|e| MLSError::openmls_error(e))
 }
 
 pub struct GroupState {
     pub group: MlsGroup,
     pub signer_public_key: Vec<u8>,
+    /// The config this group was created/joined with, retained so epoch-secret
+    /// retention (`max_past_epochs`) can be enforced after every commit
+    pub config: crate::types::GroupConfig,
+}
+
+/// A staged commit held between `process_message`/`stage_commit` and
+/// `merge_staged_commit`/`reject_staged_commit`, together with the committer
+/// metadata `StagedCommit` itself doesn't carry (it's authenticated by the
+/// message framing, not stored in the commit's proposals)
+pub struct StoredStagedCommit {
+    pub staged: Box<StagedCommit>,
+    pub sender_credential: crate::types::CredentialData,
+    pub is_external: bool,
 }
 
 pub struct MLSContextInner {
-    provider: OpenMlsRustCrypto,
+    provider: crate::group_storage::ContextProvider,
     groups: HashMap<Vec<u8>, GroupState>,
     signers_by_identity: HashMap<Vec<u8>, Vec<u8>>, // identity -> public key bytes
     pub(crate) key_package_bundles: HashMap<Vec<u8>, KeyPackageBundle>, // hash_ref -> bundle
+    /// hash_refs of bundles created with `last_resort: true`; these are
+    /// re-written into provider storage after every Welcome is processed so a
+    /// single published KeyPackage can be consumed by more than one invite
+    last_resort_bundles: std::collections::HashSet<Vec<u8>>,
     staged_welcomes: HashMap<String, StagedWelcome>,
-    staged_commits: HashMap<String, Box<StagedCommit>>,
+    staged_commits: HashMap<String, StoredStagedCommit>,
+    /// The `GroupConfig` a staged welcome was previewed with, so `join_staged_welcome`
+    /// can install it with the same forward-secrecy/wire-format settings
+    pending_welcome_configs: HashMap<String, crate::types::GroupConfig>,
     epoch_secret_manager: Arc<EpochSecretManager>,
+    /// Durable, checkpointed log of staged welcomes/commits; `None` until
+    /// `install_staged_registry` is called, in which case staged state only
+    /// lives in the in-memory maps above (matching the prior behavior)
+    staged_op_log: Option<Arc<crate::staged_registry::StagedOpLog>>,
+    /// Trusted CA certificates (DER) that incoming X.509 credentials must chain to
+    x509_trust_anchors: Vec<Vec<u8>>,
+    /// `KeyPackageDesyncDetected` failures recorded by `process_welcome`, keyed
+    /// by the synthetic `convo_id` the error carried, so `heal_key_package_desync`
+    /// can be called afterward without the caller having to thread the expected
+    /// key-package ref and identity back through itself
+    pending_key_package_desyncs: HashMap<String, PendingKeyPackageDesync>,
+    /// group ids that have changed (new group, or a `with_group` call that
+    /// mutated it) since the last `clear_dirty_groups` call
+    dirty_groups: std::collections::HashSet<Vec<u8>>,
+    /// Resumption PSKs captured by `create_group`, `join_staged_welcome`,
+    /// `join_group_by_external_commit` and the `merge_pending_commit`/
+    /// `merge_staged_commit` paths, so `branch_group`/`reinit_group` can carry
+    /// forward trust from a group's prior epoch. The epoch secret exports in
+    /// `add_members_batch`, `remove_members_batch` and `process_welcome`
+    /// capture no resumption PSK
+    resumption_psks: crate::resumption_psk::ResumptionPskStore,
+    /// Rotation history for every bundle minted by `rotate_key_packages`,
+    /// oldest first; retired entries stick around until
+    /// `garbage_collect_retired_key_packages` decides their retention window
+    /// has elapsed
+    key_package_history: Vec<KeyPackageHistoryEntry>,
+    /// Monotonic counter backing `KeyPackageHistoryEntry::rotation_index`,
+    /// so retention can also be expressed as "keep the last N rotations"
+    /// rather than only wall-clock age
+    rotation_counter: u64,
+    /// Pluggable backend for signer mappings and key package bookkeeping;
+    /// `None` until `install_key_package_metadata_storage` is called, in
+    /// which case this state only lives in the in-memory fields above and
+    /// the `serialize_storage` JSON blob (matching the prior behavior)
+    key_package_metadata_storage: Option<Arc<dyn crate::types::KeyPackageMetadataStorage>>,
+}
+
+/// A recorded `KeyPackageDesyncDetected` failure awaiting `heal_key_package_desync`
+pub(crate) struct PendingKeyPackageDesync {
+    pub identity: String,
+    pub expected_ref: Vec<u8>,
 }
 
 impl MLSContextInner {
     pub fn new() -> Self {
         Self {
-            provider: OpenMlsRustCrypto::default(),
+            provider: crate::group_storage::ContextProvider::default(),
             groups: HashMap::new(),
             signers_by_identity: HashMap::new(),
             key_package_bundles: HashMap::new(),
+            last_resort_bundles: std::collections::HashSet::new(),
             staged_welcomes: HashMap::new(),
             staged_commits: HashMap::new(),
+            pending_welcome_configs: HashMap::new(),
             epoch_secret_manager: Arc::new(EpochSecretManager::new()),
+            staged_op_log: None,
+            x509_trust_anchors: Vec::new(),
+            pending_key_package_desyncs: HashMap::new(),
+            dirty_groups: std::collections::HashSet::new(),
+            resumption_psks: crate::resumption_psk::ResumptionPskStore::new(),
+            key_package_history: Vec::new(),
+            rotation_counter: 0,
+            key_package_metadata_storage: None,
+        }
+    }
+
+    /// Install a pluggable backend that receives write-through copies of
+    /// signer mappings and `rotate_key_packages` bundle bookkeeping; the
+    /// `serialize_storage` JSON blob keeps carrying that state as well
+    ///
+    /// Call `restore_key_package_metadata` once right after this to rehydrate
+    /// the in-memory caches from whatever the backend already has stored
+    /// (e.g. from a prior run).
+    pub fn install_key_package_metadata_storage(&mut self, storage: Arc<dyn crate::types::KeyPackageMetadataStorage>) {
+        self.key_package_metadata_storage = Some(storage);
+    }
+
+    /// Rehydrate `signers_by_identity`/`key_package_history`/`last_resort_bundles`
+    /// from the installed `KeyPackageMetadataStorage` backend
+    ///
+    /// Only restores the bookkeeping this trait covers - the bundles'
+    /// private key material still needs to already be present in
+    /// `self.provider.storage()` (e.g. via a `GroupStateStorage` backend
+    /// installed and warmed up before this is called).
+    pub fn restore_key_package_metadata(&mut self) -> Result<(), MLSError> {
+        let Some(storage) = self.key_package_metadata_storage.clone() else {
+            return Ok(());
+        };
+
+        let signers = storage.list_signers()
+            .map_err(|e| MLSError::storage_error(e))?;
+        self.signers_by_identity.clear();
+        for entry in signers {
+            self.signers_by_identity.insert(entry.identity.into_bytes(), entry.signer_public_key);
+        }
+
+        let bundle_metadata = storage.list_bundle_metadata()
+            .map_err(|e| MLSError::storage_error(e))?;
+        self.key_package_history.clear();
+        self.last_resort_bundles.clear();
+        self.rotation_counter = 0;
+        for entry in bundle_metadata {
+            if entry.last_resort {
+                self.last_resort_bundles.insert(entry.hash_ref.clone());
+            }
+            self.rotation_counter = self.rotation_counter.max(entry.rotation_index);
+
+            let hash_ref_value = HashReference::from_slice(&entry.hash_ref);
+            match self.provider.storage().key_package::<HashReference, KeyPackageBundle>(&hash_ref_value) {
+                Ok(Some(bundle)) => {
+                    self.key_package_bundles.insert(entry.hash_ref.clone(), bundle);
+                }
+                Ok(None) => {
+                    crate::warn_log!("[MLS-CONTEXT] restore_key_package_metadata: bundle for hash_ref {} not found in provider storage", hex::encode(&entry.hash_ref));
+                }
+                Err(e) => {
+                    crate::warn_log!("[MLS-CONTEXT] restore_key_package_metadata: failed to query provider storage for hash_ref {}: {:?}", hex::encode(&entry.hash_ref), e);
+                }
+            }
+
+            self.key_package_history.push(KeyPackageHistoryEntry {
+                identity: entry.identity,
+                hash_ref: entry.hash_ref,
+                created_at_unix_secs: entry.created_at_unix_secs,
+                rotation_index: entry.rotation_index,
+                retired: entry.retired,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Best-effort write-through to the installed `KeyPackageMetadataStorage`
+    /// backend, if any; failures are logged, never fatal, matching how
+    /// `reinstate_last_resort_bundles` treats its own storage writes
+    fn write_through_signer(&self, identity: &str, signer_public_key: &[u8]) {
+        let Some(storage) = &self.key_package_metadata_storage else { return };
+        if let Err(e) = storage.put_signer(crate::types::SignerEntry {
+            identity: identity.to_string(),
+            signer_public_key: signer_public_key.to_vec(),
+        }) {
+            crate::warn_log!("[MLS-CONTEXT] write_through_signer: failed to persist signer for '{}': {:?}", identity, e);
+        }
+    }
+
+    /// Best-effort write-through of one bundle's bookkeeping; see
+    /// `write_through_signer`
+    fn write_through_bundle_metadata(&self, entry: &KeyPackageHistoryEntry, last_resort: bool) {
+        let Some(storage) = &self.key_package_metadata_storage else { return };
+        if let Err(e) = storage.put_bundle_metadata(crate::types::KeyPackageMetadataEntry {
+            hash_ref: entry.hash_ref.clone(),
+            identity: entry.identity.clone(),
+            last_resort,
+            created_at_unix_secs: entry.created_at_unix_secs,
+            rotation_index: entry.rotation_index,
+            retired: entry.retired,
+        }) {
+            crate::warn_log!("[MLS-CONTEXT] write_through_bundle_metadata: failed to persist bundle {}: {:?}", hex::encode(&entry.hash_ref), e);
         }
     }
 
+    /// Configure the CA certificates (DER) that X.509 key packages must chain to
+    pub fn set_x509_trust_anchors(&mut self, trust_anchors: Vec<Vec<u8>>) {
+        self.x509_trust_anchors = trust_anchors;
+    }
+
+    /// Verify that an X.509 credential's certificate chain terminates at one
+    /// of the configured trust anchors
+    ///
+    /// This checks that the chain's root certificate byte-matches a configured
+    /// anchor. It does not perform full path validation (signature chaining,
+    /// expiry, name constraints) — that belongs in a dedicated X.509 library —
+    /// but it is enough to reject key packages issued under an unrecognized CA.
+    pub fn verify_x509_credential(&self, credential: &Credential) -> Result<(), MLSError> {
+        if self.x509_trust_anchors.is_empty() {
+            // No trust anchors configured: nothing to enforce yet
+            return Ok(());
+        }
+
+        if credential.credential_type() != CredentialType::X509 {
+            return Ok(());
+        }
+
+        let chain = Self::decode_x509_chain(credential.serialized_content());
+        let root = chain.last().ok_or_else(|| MLSError::invalid_input("X.509 credential has an empty certificate chain"))?;
+
+        if self.x509_trust_anchors.iter().any(|anchor| anchor == root) {
+            Ok(())
+        } else {
+            Err(MLSError::invalid_input("X.509 certificate chain does not terminate at a trusted anchor"))
+        }
+    }
+
+    /// Decode a length-prefixed DER certificate chain back into individual certificates
+    fn decode_x509_chain(bytes: &[u8]) -> Vec<Vec<u8>> {
+        let mut certs = Vec::new();
+        let mut offset = 0;
+        while offset + 4 <= bytes.len() {
+            let len = u32::from_be_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]]) as usize;
+            offset += 4;
+            if offset + len > bytes.len() {
+                break;
+            }
+            certs.push(bytes[offset..offset + len].to_vec());
+            offset += len;
+        }
+        certs
+    }
+
     /// Get reference to epoch secret manager for setting storage backend
     pub fn epoch_secret_manager(&self) -> &Arc<EpochSecretManager> {
         &self.epoch_secret_manager
     }
 
-    pub fn provider(&self) -> &OpenMlsRustCrypto {
+    /// Record a resumption PSK captured for `group_id` at `epoch`
+    pub(crate) fn capture_resumption_psk(&mut self, group_id: &[u8], epoch: u64, secret: Vec<u8>) {
+        self.resumption_psks.capture(group_id, epoch, secret);
+    }
+
+    /// The most recently captured `(epoch, secret)` resumption PSK for `group_id`
+    pub(crate) fn latest_resumption_psk(&self, group_id: &[u8]) -> Option<(u64, Vec<u8>)> {
+        self.resumption_psks.latest(group_id).map(|(epoch, secret)| (epoch, secret.clone()))
+    }
+
+    /// Record that a cached bundle was built with the `last_resort` extension,
+    /// so `reinstate_last_resort_bundles` knows to keep re-writing it
+    pub fn mark_last_resort_bundle(&mut self, hash_ref: Vec<u8>) {
+        self.last_resort_bundles.insert(hash_ref);
+    }
+
+    /// List the hash_ref and last-resort status of every cached key package bundle
+    ///
+    /// Lets the app tell which cached bundles are regular (single-use, worth
+    /// replenishing once consumed) versus the stable last-resort fallback it
+    /// should leave in place.
+    pub fn list_key_package_bundles(&self) -> Vec<crate::types::KeyPackageBundleInfo> {
+        self.key_package_bundles
+            .keys()
+            .map(|hash_ref| crate::types::KeyPackageBundleInfo {
+                hash_ref: hash_ref.clone(),
+                last_resort: self.last_resort_bundles.contains(hash_ref),
+            })
+            .collect()
+    }
+
+    /// Record a `KeyPackageDesyncDetected` failure so `heal_key_package_desync`
+    /// can act on it later without the caller re-supplying the identity/expected ref
+    pub(crate) fn record_key_package_desync(&mut self, convo_id: String, identity: String, expected_ref: Vec<u8>) {
+        self.pending_key_package_desyncs.insert(convo_id, PendingKeyPackageDesync { identity, expected_ref });
+    }
+
+    /// Remove and return the desync recorded for `convo_id`, if any
+    pub(crate) fn take_key_package_desync(&mut self, convo_id: &str) -> Option<PendingKeyPackageDesync> {
+        self.pending_key_package_desyncs.remove(convo_id)
+    }
+
+    /// Re-write every last-resort bundle into provider storage
+    ///
+    /// OpenMLS deletes a KeyPackage's private HPKE key from storage once a
+    /// Welcome consumes it, which is correct for single-use KeyPackages but
+    /// breaks a last-resort KeyPackage that is meant to back more than one
+    /// invite. The bundle's private material never left our own cache, so
+    /// after every Welcome/StagedWelcome join this writes each last-resort
+    /// bundle back into storage, undoing that deletion for the ones that are
+    /// supposed to survive it. Best-effort: logged, never fails the join.
+    pub fn reinstate_last_resort_bundles(&self) {
+        for hash_ref in &self.last_resort_bundles {
+            let Some(bundle) = self.key_package_bundles.get(hash_ref) else { continue };
+            let hash_ref_value = HashReference::from_slice(hash_ref);
+            if let Err(e) = self.provider.storage().write_key_package(&hash_ref_value, bundle) {
+                crate::warn_log!("[MLS-CONTEXT] ⚠️ Failed to reinstate last-resort bundle {}: {:?}", hex::encode(hash_ref), e);
+            }
+        }
+    }
+
+    /// Install a durable backend for the staged-welcome/staged-commit registry
+    ///
+    /// Once installed, every `store_staged_welcome`/`store_staged_commit` call
+    /// also appends a log entry through the callback so staged state survives
+    /// an app restart; call `replay_staged_operations` after a cold start to
+    /// see which entries still need rehydrating.
+    pub fn install_staged_registry(&mut self, storage: Arc<dyn crate::types::GroupStateStorage>) {
+        self.staged_op_log = Some(Arc::new(crate::staged_registry::StagedOpLog::new(storage)));
+    }
+
+    /// Replay the durable log of not-yet-completed staged operations for a group
+    ///
+    /// Returns an empty vec if no registry has been installed. The caller is
+    /// responsible for re-parsing each entry's raw Welcome/commit bytes back
+    /// into a `StagedWelcome`/`StagedCommit` and re-populating the in-memory
+    /// maps via `store_staged_welcome`/`store_staged_commit`.
+    pub fn replay_staged_operations(&self, group_id: &[u8]) -> Result<Vec<crate::staged_registry::StagedLogEntry>, MLSError> {
+        match &self.staged_op_log {
+            Some(log) => log.replay(group_id),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    pub fn provider(&self) -> &crate::group_storage::ContextProvider {
         &self.provider
     }
 
+    /// Switch OpenMLS's storage reads/writes over to a `GroupStateStorage`
+    /// callback, in place of the default in-memory `MemoryStorage`
+    ///
+    /// Must be called before any group is created or joined: swapping the
+    /// backend out from under an already-running group would strand
+    /// whatever it had already written to the old one. `serialize_storage`/
+    /// `deserialize_storage` stop working once this is installed, since the
+    /// callback backend is expected to persist incrementally on its own.
+    pub fn install_storage_provider(&mut self, storage: Arc<dyn crate::types::GroupStateStorage>) {
+        self.provider.install_callback_storage(storage);
+    }
+
+    /// Encode a DER certificate chain (leaf-first) into the byte layout used
+    /// as an X.509 `Credential`'s serialized content: a length-prefixed list
+    /// of DER certificates
+    fn encode_x509_chain(cert_chain: &[Vec<u8>]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for cert in cert_chain {
+            bytes.extend_from_slice(&(cert.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(cert);
+        }
+        bytes
+    }
+
+    /// Build a `Credential` for `identity` according to `credential_type`
+    ///
+    /// Shared by `create_group` and `create_key_package` so both support the
+    /// same Basic/X.509 choice instead of `create_key_package` hardcoding Basic.
+    pub(crate) fn build_credential(identity: &str, credential_type: &crate::types::CredentialTypeSelector) -> Result<Credential, MLSError> {
+        match credential_type {
+            crate::types::CredentialTypeSelector::Basic => {
+                Ok(Credential::new(CredentialType::Basic, identity.as_bytes().to_vec()))
+            }
+            crate::types::CredentialTypeSelector::X509 { cert_chain } => {
+                if cert_chain.is_empty() {
+                    return Err(MLSError::invalid_input("X.509 credential requires a non-empty certificate chain"));
+                }
+                let chain_bytes = Self::encode_x509_chain(cert_chain);
+                Ok(Credential::new(CredentialType::X509, chain_bytes))
+            }
+        }
+    }
+
+    /// Extract the comparison key used to detect duplicate members for a credential
+    ///
+    /// Basic credentials compare by their raw identity bytes; X.509 credentials
+    /// compare by the leaf certificate's bytes (its subject/SPKI), since two
+    /// certificates for the same identity re-issued from a different chain
+    /// should still be treated as distinct unless they share the same leaf.
+    pub fn credential_comparison_key(credential: &Credential) -> Vec<u8> {
+        match credential.credential_type() {
+            CredentialType::X509 => {
+                let chain = credential.serialized_content();
+                // First length-prefixed entry is the leaf certificate
+                if chain.len() >= 4 {
+                    let leaf_len = u32::from_be_bytes([chain[0], chain[1], chain[2], chain[3]]) as usize;
+                    if chain.len() >= 4 + leaf_len {
+                        return chain[4..4 + leaf_len].to_vec();
+                    }
+                }
+                chain.to_vec()
+            }
+            _ => credential.serialized_content().to_vec(),
+        }
+    }
+
     pub fn create_group(&mut self, identity: &str, config: crate::types::GroupConfig) -> Result<Vec<u8>, MLSError> {
         crate::debug_log!("[MLS-CONTEXT] create_group: Starting for identity '{}'", identity);
-        
-        let credential = Credential::new(
-            CredentialType::Basic,
-            identity.as_bytes().to_vec()
-        );
+
+        let credential = Self::build_credential(identity, &config.credential_type)?;
         crate::debug_log!("[MLS-CONTEXT] Credential created");
-        
+
+        let ciphersuite = ciphersuite_for(&config.ciphersuite);
+
         crate::debug_log!("[MLS-CONTEXT] Generating signature keys...");
-        let signature_keys = SignatureKeyPair::new(SignatureScheme::ED25519)
+        let signature_keys = SignatureKeyPair::new(ciphersuite.signature_algorithm())
             .map_err(|e| {
                 crate::debug_log!("[MLS-CONTEXT] ERROR: Failed to create signature keys: {:?}", e);
-                MLSError::OpenMLSError
+                MLSError::openmls_error(e)
             })?;
         crate::debug_log!("[MLS-CONTEXT] Signature keys generated");
 
@@ -92,7 +725,7 @@ impl MLSContextInner {
         signature_keys.store(self.provider.storage())
             .map_err(|e| {
                 crate::debug_log!("[MLS-CONTEXT] ERROR: Failed to store signature keys: {:?}", e);
-                MLSError::OpenMLSError
+                MLSError::openmls_error(e)
             })?;
         crate::debug_log!("[MLS-CONTEXT] Signature keys stored");
 
@@ -110,12 +743,13 @@ impl MLSContextInner {
         );
 
         let group_config = MlsGroupCreateConfig::builder()
+            .ciphersuite(ciphersuite)
             .max_past_epochs(config.max_past_epochs as usize)
             .sender_ratchet_configuration(SenderRatchetConfiguration::new(
                 config.out_of_order_tolerance,
                 config.maximum_forward_distance,
             ))
-            .wire_format_policy(PURE_CIPHERTEXT_WIRE_FORMAT_POLICY)
+            .wire_format_policy(wire_format_policy_for(&config.wire_format_policy))
             .capabilities(capabilities)  // Set required capabilities
             .use_ratchet_tree_extension(true)  // CRITICAL: Include ratchet tree in Welcome messages
             .build();
@@ -133,7 +767,7 @@ impl MLSContextInner {
         )
         .map_err(|e| {
             crate::debug_log!("[MLS-CONTEXT] ERROR: Failed to create MLS group: {:?}", e);
-            MLSError::OpenMLSError
+            MLSError::openmls_error(e)
         })?;
         crate::debug_log!("[MLS-CONTEXT] MLS group created successfully");
 
@@ -157,10 +791,17 @@ impl MLSContextInner {
             crate::debug_log!("[MLS-CONTEXT] ✅ Exported epoch {} secret successfully", current_epoch);
         }
 
+        match export_resumption_psk(&group, &self.provider) {
+            Ok(secret) => self.resumption_psks.capture(&group_id, current_epoch, secret),
+            Err(e) => crate::warn_log!("[MLS-CONTEXT] WARNING: Failed to capture resumption PSK for epoch {}: {:?}", current_epoch, e),
+        }
+
         self.groups.insert(group_id.clone(), GroupState {
             group,
             signer_public_key: signature_keys.public().to_vec(),
+            config,
         });
+        self.dirty_groups.insert(group_id.clone());
         crate::debug_log!("[MLS-CONTEXT] Group state stored");
 
         self.signers_by_identity.insert(identity.as_bytes().to_vec(), signature_keys.public().to_vec());
@@ -170,27 +811,296 @@ impl MLSContextInner {
         Ok(group_id)
     }
 
-    pub fn add_group(&mut self, group: MlsGroup, identity: &str) -> Result<(), MLSError> {
+    pub fn add_group(&mut self, group: MlsGroup, identity: &str, config: crate::types::GroupConfig) -> Result<(), MLSError> {
         let signer_pk = self.signers_by_identity
             .get(identity.as_bytes())
             .ok_or_else(|| MLSError::group_not_found(format!("No signer for identity: {}", identity)))?
             .clone();
 
         let group_id = group.group_id().as_slice().to_vec();
+        self.dirty_groups.insert(group_id.clone());
         self.groups.insert(group_id, GroupState {
             group,
-            signer_public_key: signer_pk
+            signer_public_key: signer_pk,
+            config,
         });
         Ok(())
     }
 
+    /// group ids that have changed since the last `clear_dirty_groups` call
+    ///
+    /// Lets a caller on the in-memory storage backend check whether anything
+    /// changed before paying for a full `serialize_storage` pass, turning
+    /// "persist on every tick" into "persist only when something is dirty".
+    /// This is coarser than true per-group serialization: `MemoryStorage`'s
+    /// own serialize format isn't addressable per group, so a dirty group
+    /// still means re-serializing everything, not just that group. A caller
+    /// that needs real O(changed group) persistence should install a
+    /// `GroupStateStorage` callback via `install_storage_provider` instead,
+    /// which already writes each changed key incrementally as it happens.
+    pub fn dirty_group_ids(&self) -> Vec<Vec<u8>> {
+        self.dirty_groups.iter().cloned().collect()
+    }
+
+    /// Clear the dirty set, typically right after a successful persist
+    pub fn clear_dirty_groups(&mut self) {
+        self.dirty_groups.clear();
+    }
+
     /// Register a signer public key for an identity
     /// This must be called when creating key packages so the signer can be found when processing Welcome messages
     pub fn register_signer(&mut self, identity: &str, signer_public_key: Vec<u8>) {
-        self.signers_by_identity.insert(identity.as_bytes().to_vec(), signer_public_key);
+        self.signers_by_identity.insert(identity.as_bytes().to_vec(), signer_public_key.clone());
+        self.write_through_signer(identity, &signer_public_key);
         crate::debug_log!("[MLS-CONTEXT] Registered signer for identity: {}", identity);
     }
 
+    /// Mint a fresh key package bundle for `identity` and cache it, exactly
+    /// like `create_key_package` does, with the given `ciphersuite`/
+    /// `credential_type` - shared by `deserialize_storage` (which carries
+    /// forward the original bundle's provenance where it's known) and
+    /// `heal_key_package_desync` (which has no prior bundle to go on and
+    /// falls back to the default/Basic the way this used to unconditionally,
+    /// and always wants `last_resort` since it has no roster to hand the new
+    /// key package to directly).
+    pub(crate) fn regenerate_key_package_bundle(
+        &mut self,
+        identity: &str,
+        ciphersuite: &crate::types::CiphersuiteSelector,
+        credential_type: &crate::types::CredentialTypeSelector,
+        last_resort: bool,
+    ) -> Result<crate::types::RegeneratedKeyPackage, MLSError> {
+        let ciphersuite = ciphersuite_for(ciphersuite);
+        let credential = Self::build_credential(identity, credential_type)?;
+
+        let signature_keys = SignatureKeyPair::new(ciphersuite.signature_algorithm())
+            .map_err(|e| MLSError::openmls_error(e))?;
+        signature_keys.store(self.provider.storage())
+            .map_err(|e| MLSError::openmls_error(e))?;
+        self.register_signer(identity, signature_keys.public().to_vec());
+
+        let mut builder = KeyPackage::builder();
+        if last_resort {
+            builder = builder.mark_as_last_resort();
+        }
+        let key_package_bundle = builder
+            .build(
+                ciphersuite,
+                &self.provider,
+                &signature_keys,
+                CredentialWithKey {
+                    credential,
+                    signature_key: signature_keys.public().into(),
+                },
+            )
+            .map_err(|e| MLSError::openmls_error(e))?;
+
+        let key_package = key_package_bundle.key_package().clone();
+        let key_package_data = key_package.tls_serialize_detached()
+            .map_err(|e| MLSError::serialization_error(e))?;
+        let hash_ref = key_package.hash_ref(self.provider.crypto())
+            .map_err(|e| MLSError::openmls_error(e))?
+            .as_slice()
+            .to_vec();
+
+        self.key_package_bundles.insert(hash_ref.clone(), key_package_bundle);
+        if last_resort {
+            self.mark_last_resort_bundle(hash_ref.clone());
+        }
+
+        Ok(crate::types::RegeneratedKeyPackage {
+            identity: identity.to_string(),
+            hash_ref,
+            key_package_data,
+        })
+    }
+
+    /// Recover the identity/ciphersuite/credential-type a still-live `bundle`
+    /// was built with, for persisting alongside its hash_ref in
+    /// `serialize_storage` so a future `deserialize_storage` can regenerate a
+    /// faithful replacement instead of a hardcoded default if this bundle
+    /// later turns out missing
+    ///
+    /// The identity is recovered by matching the bundle's own signature key
+    /// against `signers_by_identity` (populated by `register_signer` at
+    /// creation time) rather than the credential, since a Basic credential's
+    /// serialized content happens to be the identity but an X.509 one is a
+    /// cert chain with no identity embedded at all. Falls back to an empty
+    /// identity (meaning "can't recover, skip" to `deserialize_storage`) if no
+    /// match is found, and to the default ciphersuite/Basic credential if the
+    /// suite isn't one `ciphersuite_selector_for` recognizes.
+    fn bundle_provenance(
+        &self,
+        bundle: &KeyPackageBundle,
+    ) -> (String, crate::types::CiphersuiteSelector, crate::types::CredentialTypeSelector) {
+        let key_package = bundle.key_package();
+        let leaf_node = key_package.leaf_node();
+        let signature_key = leaf_node.signature_key().as_slice();
+
+        let identity = self.signers_by_identity.iter()
+            .find(|(_, signer_public_key)| signer_public_key.as_slice() == signature_key)
+            .and_then(|(identity_bytes, _)| String::from_utf8(identity_bytes.clone()).ok())
+            .unwrap_or_default();
+
+        let ciphersuite = ciphersuite_selector_for(key_package.ciphersuite())
+            .unwrap_or_default();
+
+        let credential = leaf_node.credential();
+        let credential_type = match credential.credential_type() {
+            CredentialType::X509 => crate::types::CredentialTypeSelector::X509 {
+                cert_chain: Self::decode_x509_chain(credential.serialized_content()),
+            },
+            _ => crate::types::CredentialTypeSelector::Basic,
+        };
+
+        (identity, ciphersuite, credential_type)
+    }
+
+    /// Mint a fresh key package bundle for `identity` and retire its
+    /// predecessors instead of deleting them outright
+    ///
+    /// The new bundle is cached and written to provider storage exactly like
+    /// `create_key_package`. Every bundle this identity previously rotated in
+    /// is marked `retired` in the history log - still present in
+    /// `key_package_bundles`/provider storage, so a Welcome sent against an
+    /// older published KeyPackage still decrypts - and only removed once
+    /// `retention` judges it's aged out, which this call also checks on every
+    /// invocation so retired bundles don't accumulate forever.
+    pub fn rotate_key_packages(
+        &mut self,
+        identity: &str,
+        ciphersuite: crate::types::CiphersuiteSelector,
+        credential_type: &crate::types::CredentialTypeSelector,
+        retention: crate::types::KeyPackageRetentionPolicy,
+    ) -> Result<crate::types::KeyPackageRotationResult, MLSError> {
+        for entry in self.key_package_history.iter_mut() {
+            if entry.identity == identity {
+                entry.retired = true;
+            }
+        }
+        for entry in self.key_package_history.iter().filter(|e| e.identity == identity) {
+            self.write_through_bundle_metadata(entry, self.last_resort_bundles.contains(&entry.hash_ref));
+        }
+
+        let suite = ciphersuite_for(&ciphersuite);
+        let credential = Self::build_credential(identity, credential_type)?;
+
+        let signature_keys = SignatureKeyPair::new(suite.signature_algorithm())
+            .map_err(|e| MLSError::openmls_error(e))?;
+        signature_keys.store(self.provider.storage())
+            .map_err(|e| MLSError::openmls_error(e))?;
+        self.register_signer(identity, signature_keys.public().to_vec());
+
+        let key_package_bundle = KeyPackage::builder()
+            .build(
+                suite,
+                &self.provider,
+                &signature_keys,
+                CredentialWithKey {
+                    credential,
+                    signature_key: signature_keys.public().into(),
+                },
+            )
+            .map_err(|e| MLSError::openmls_error(e))?;
+
+        let key_package = key_package_bundle.key_package().clone();
+        let key_package_data = key_package.tls_serialize_detached()
+            .map_err(|e| MLSError::serialization_error(e))?;
+        let hash_ref = key_package.hash_ref(self.provider.crypto())
+            .map_err(|e| MLSError::openmls_error(e))?
+            .as_slice()
+            .to_vec();
+
+        self.key_package_bundles.insert(hash_ref.clone(), key_package_bundle);
+
+        self.rotation_counter += 1;
+        let new_entry = KeyPackageHistoryEntry {
+            identity: identity.to_string(),
+            hash_ref: hash_ref.clone(),
+            created_at_unix_secs: now_unix_secs(),
+            rotation_index: self.rotation_counter,
+            retired: false,
+        };
+        self.write_through_bundle_metadata(&new_entry, false);
+        self.key_package_history.push(new_entry);
+
+        let garbage_collected = self.garbage_collect_retired_key_packages(identity, &retention);
+
+        Ok(crate::types::KeyPackageRotationResult {
+            key_package_data,
+            hash_ref,
+            garbage_collected,
+        })
+    }
+
+    /// Permanently delete every retired bundle for `identity` whose retention
+    /// window (`min_rotations` AND `min_age_seconds`, both satisfied) has elapsed
+    ///
+    /// Best-effort on the storage delete: a failure there is logged but still
+    /// drops the entry from the in-memory history/cache, since the bundle's
+    /// private key material is no longer reachable through this crate either
+    /// way once it's out of `key_package_history`.
+    fn garbage_collect_retired_key_packages(&mut self, identity: &str, retention: &crate::types::KeyPackageRetentionPolicy) -> Vec<Vec<u8>> {
+        let now = now_unix_secs();
+        let history = self.key_package_history.clone();
+
+        let (expired, kept): (Vec<_>, Vec<_>) = self.key_package_history
+            .drain(..)
+            .partition(|entry| {
+                let newer_rotations = history.iter()
+                    .filter(|other| other.identity == entry.identity && other.rotation_index > entry.rotation_index)
+                    .count() as u64;
+                entry.identity == identity
+                    && entry.retired
+                    && newer_rotations >= retention.min_rotations as u64
+                    && now.saturating_sub(entry.created_at_unix_secs) >= retention.min_age_seconds
+            });
+
+        self.key_package_history = kept;
+
+        let mut garbage_collected = Vec::new();
+        for entry in expired {
+            let hash_ref_value = HashReference::from_slice(&entry.hash_ref);
+            if let Err(e) = self.provider.storage().delete_key_package(&hash_ref_value) {
+                crate::warn_log!("[MLS-CONTEXT] garbage_collect_retired_key_packages: failed to delete bundle {} from storage: {:?}", hex::encode(&entry.hash_ref), e);
+            }
+            if let Some(storage) = &self.key_package_metadata_storage {
+                if let Err(e) = storage.delete_bundle_metadata(entry.hash_ref.clone()) {
+                    crate::warn_log!("[MLS-CONTEXT] garbage_collect_retired_key_packages: failed to delete bundle metadata {}: {:?}", hex::encode(&entry.hash_ref), e);
+                }
+            }
+            self.key_package_bundles.remove(&entry.hash_ref);
+            garbage_collected.push(entry.hash_ref);
+        }
+
+        garbage_collected
+    }
+
+    /// Look up the `GroupConfig` a group was created/joined with
+    pub fn config_for_group(&self, group_id: &GroupId) -> Result<crate::types::GroupConfig, MLSError> {
+        self.groups
+            .get(group_id.as_slice())
+            .map(|state| state.config.clone())
+            .ok_or_else(|| MLSError::group_not_found(hex::encode(group_id.as_slice())))
+    }
+
+    /// Change how many past epochs' secrets this context keeps retained for `group_id`
+    ///
+    /// This only governs our own exported-secret retention (what `enforce_retention`/
+    /// `prune_epoch_secrets` keep in `EpochSecretStorage`, and how far back
+    /// `check_epoch_in_retained_window` will allow a late message). OpenMLS's own
+    /// in-process secret tree window was fixed by `max_past_epochs` at
+    /// `create_group`/`join_staged_welcome` time and can't be widened after the
+    /// fact without rejoining, so raising this value past that original window
+    /// doesn't let OpenMLS itself decrypt any further back than it already could.
+    pub fn set_max_retained_epochs(&mut self, group_id: &GroupId, max_past_epochs: u32) -> Result<(), MLSError> {
+        let state = self.groups
+            .get_mut(group_id.as_slice())
+            .ok_or_else(|| MLSError::group_not_found(hex::encode(group_id.as_slice())))?;
+        state.config.max_past_epochs = max_past_epochs;
+        Ok(())
+    }
+
     pub fn signer_for_group(&self, group_id: &GroupId) -> Result<SignatureKeyPair, MLSError> {
         let state = self.groups
             .get(group_id.as_slice())
@@ -198,14 +1108,14 @@ impl MLSContextInner {
         
         // Load signer from storage using public key
         SignatureKeyPair::read(
-            self.provider.storage(), 
+            self.provider.storage(),
             &state.signer_public_key,
-            SignatureScheme::ED25519
+            ciphersuite_for(&state.config.ciphersuite).signature_algorithm()
         )
-            .ok_or_else(|| MLSError::OpenMLSError)
+            .ok_or_else(|| MLSError::openmls_error("signer key pair not found in storage"))
     }
 
-    pub fn with_group<T, F: FnOnce(&mut MlsGroup, &OpenMlsRustCrypto, &SignatureKeyPair) -> Result<T, MLSError>>(
+    pub fn with_group<T, F: FnOnce(&mut MlsGroup, &crate::group_storage::ContextProvider, &SignatureKeyPair) -> Result<T, MLSError>>(
         &mut self,
         group_id: &GroupId,
         f: F,
@@ -230,20 +1140,24 @@ impl MLSContextInner {
         // Load signer from storage
         crate::debug_log!("[MLS-CONTEXT] Loading signer from storage...");
         let signer = SignatureKeyPair::read(
-            self.provider.storage(), 
+            self.provider.storage(),
             &state.signer_public_key,
-            SignatureScheme::ED25519
+            ciphersuite_for(&state.config.ciphersuite).signature_algorithm()
         )
             .ok_or_else(|| {
                 crate::debug_log!("[MLS-CONTEXT] ERROR: Failed to load signer from storage");
-                MLSError::OpenMLSError
+                MLSError::openmls_error("signer key pair not found in storage")
             })?;
         crate::debug_log!("[MLS-CONTEXT] Signer loaded successfully");
-        
-        f(&mut state.group, &self.provider, &signer)
+
+        let result = f(&mut state.group, &self.provider, &signer);
+        if result.is_ok() {
+            self.dirty_groups.insert(group_id.as_slice().to_vec());
+        }
+        result
     }
 
-    pub fn with_group_ref<T, F: FnOnce(&MlsGroup, &OpenMlsRustCrypto) -> Result<T, MLSError>>(
+    pub fn with_group_ref<T, F: FnOnce(&MlsGroup, &crate::group_storage::ContextProvider) -> Result<T, MLSError>>(
         &self,
         group_id: &GroupId,
         f: F,
@@ -254,22 +1168,418 @@ impl MLSContextInner {
         f(&state.group, &self.provider)
     }
 
-    pub fn store_staged_welcome(&mut self, id: String, staged: StagedWelcome) {
+    /// Preview a Welcome message without installing any group state
+    ///
+    /// Deserializes the `MlsMessageIn` and builds the `StagedWelcome` the same
+    /// way `process_welcome` does, but stops short of `into_group`: no group is
+    /// installed and the group does not become usable; the only write is the
+    /// staged-log entry `store_staged_welcome` appends when a staged registry
+    /// is installed. The resulting
+    /// `StagedWelcome` is cached under a content-addressed id (alongside the
+    /// config it was built with) so a following `join_staged_welcome` call can
+    /// finish the join without re-deriving the GroupSecrets.
+    pub fn inspect_welcome(&mut self, welcome_bytes: &[u8], config: crate::types::GroupConfig) -> Result<crate::types::StagedWelcomeInfo, MLSError> {
+        let (mls_msg, _) = MlsMessageIn::tls_deserialize_bytes(welcome_bytes)
+            .map_err(|e| MLSError::serialization_error(e))?;
+
+        let welcome = match mls_msg.extract() {
+            MlsMessageBodyIn::Welcome(w) => w,
+            _ => return Err(MLSError::invalid_input("Not a Welcome message")),
+        };
+
+        // The recipient's `KeyPackageRef` is visible on each `EncryptedGroupSecrets`
+        // entry without decrypting anything, so the matching cached bundle can be
+        // reported even though `StagedWelcome` itself doesn't expose it
+        let matched_key_package_hash_ref = welcome.secrets().iter().find_map(|egs| {
+            let candidate = egs.new_member().as_slice().to_vec();
+            self.key_package_bundles.contains_key(&candidate).then_some(candidate)
+        });
+
+        let join_config = MlsGroupJoinConfig::builder()
+            .max_past_epochs(config.max_past_epochs as usize)
+            .sender_ratchet_configuration(SenderRatchetConfiguration::new(
+                config.out_of_order_tolerance,
+                config.maximum_forward_distance,
+            ))
+            .wire_format_policy(wire_format_policy_for(&config.wire_format_policy))
+            .build();
+
+        let staged = StagedWelcome::new_from_welcome(&self.provider, &join_config, welcome, None)
+            .map_err(|e| {
+                crate::debug_log!("[MLS-CONTEXT] ERROR: StagedWelcome::new_from_welcome failed: {:?}", e);
+                MLSError::openmls_error(e)
+            })?;
+
+        let group_context = staged.group_context();
+        let group_id = group_context.group_id().as_slice().to_vec();
+        let ciphersuite = u16::from(group_context.ciphersuite());
+        let protocol_version = u16::from(group_context.protocol_version());
+        let epoch = group_context.epoch().as_u64();
+
+        let member_credentials: Vec<crate::types::MemberCredential> = staged
+            .members()
+            .map(|member| crate::types::MemberCredential {
+                leaf_index: member.index.u32(),
+                credential: crate::types::CredentialData {
+                    credential_type: format!("{:?}", member.credential.credential_type()),
+                    identity: member.credential.serialized_content().to_vec(),
+                },
+                signature_key: member.signature_key,
+            })
+            .collect();
+
+        // OpenMLS doesn't expose the committing sender's identity separately from
+        // the member list pre-join, so the lowest leaf index (the longest-standing
+        // member, typically the group's creator) is used as a best-effort stand-in
+        let sender_credential = member_credentials
+            .first()
+            .map(|m| m.credential.clone())
+            .unwrap_or(crate::types::CredentialData {
+                credential_type: "Unknown".to_string(),
+                identity: Vec::new(),
+            });
+
+        let staged_welcome_id = hex::encode(
+            self.provider.crypto()
+                .hash(group_context.ciphersuite().hash_algorithm(), welcome_bytes)
+                .map_err(|e| MLSError::openmls_error(e))?
+        );
+
+        self.pending_welcome_configs.insert(staged_welcome_id.clone(), config);
+        self.store_staged_welcome(staged_welcome_id.clone(), &group_id, welcome_bytes.to_vec(), staged);
+
+        Ok(crate::types::StagedWelcomeInfo {
+            group_id,
+            ciphersuite,
+            protocol_version,
+            epoch,
+            sender_credential,
+            member_credentials,
+            matched_key_package_hash_ref,
+            staged_welcome_id,
+        })
+    }
+
+    /// Recover the expected recipient `KeyPackageRef` from a Welcome without
+    /// decrypting anything
+    ///
+    /// `EncryptedGroupSecrets::new_member` is sent in the clear, so it's
+    /// readable even when we hold none of the key package bundles it could be
+    /// decrypted with. Used to give the key-package-desync error a stable
+    /// identifier tied to the actual Welcome content instead of hashing
+    /// arbitrary raw bytes; the real group ID stays inside the encrypted
+    /// `GroupInfo` and isn't recoverable without a matching bundle.
+    pub fn peek_welcome_key_package_ref(welcome_bytes: &[u8]) -> Option<Vec<u8>> {
+        let (mls_msg, _) = MlsMessageIn::tls_deserialize_bytes(welcome_bytes).ok()?;
+        let welcome = match mls_msg.extract() {
+            MlsMessageBodyIn::Welcome(w) => w,
+            _ => return None,
+        };
+        welcome
+            .secrets()
+            .first()
+            .map(|egs| egs.new_member().as_slice().to_vec())
+    }
+
+    /// Finish joining a group previously previewed with `inspect_welcome`
+    ///
+    /// Installs the cached `StagedWelcome`, exporting its current epoch secret
+    /// and registering it under `identity` exactly as `process_welcome` would.
+    pub fn join_staged_welcome(&mut self, group_id: &[u8], staged_welcome_id: &str, identity: &str) -> Result<Vec<u8>, MLSError> {
+        let staged = self.take_staged_welcome(staged_welcome_id, group_id)?;
+        let config = self.pending_welcome_configs.remove(staged_welcome_id).unwrap_or_default();
+
+        let group = staged.into_group(&self.provider)
+            .map_err(|e| {
+                crate::debug_log!("[MLS-CONTEXT] ERROR: StagedWelcome::into_group failed: {:?}", e);
+                MLSError::openmls_error(e)
+            })?;
+
+        let resolved_group_id = group.group_id().as_slice().to_vec();
+
+        if let Err(e) = self.epoch_secret_manager.export_current_epoch_secret(&group, &self.provider) {
+            crate::warn_log!("[MLS-CONTEXT] WARNING: Failed to export epoch secret after joining staged welcome: {:?}", e);
+        }
+
+        match export_resumption_psk(&group, &self.provider) {
+            Ok(secret) => self.resumption_psks.capture(&resolved_group_id, group.epoch().as_u64(), secret),
+            Err(e) => crate::warn_log!("[MLS-CONTEXT] WARNING: Failed to capture resumption PSK after joining staged welcome: {:?}", e),
+        }
+
+        self.add_group(group, identity, config)?;
+        self.reinstate_last_resort_bundles();
+
+        Ok(resolved_group_id)
+    }
+
+    /// Join a group by external commit using a published `GroupInfo`, instead
+    /// of requiring an existing member to send a Welcome
+    ///
+    /// `group_info_bytes` and `ratchet_tree_bytes` are whatever an existing
+    /// member published alongside an invite link/QR code (a `GroupInfo`
+    /// message and the corresponding ratchet tree). Builds the external
+    /// commit with `identity`'s already-registered signer, installs the
+    /// resulting group exactly as a Welcome join would, and returns the new
+    /// group id together with the commit message the caller must broadcast
+    /// so existing members merge it and learn of the new member.
+    pub fn join_group_by_external_commit(
+        &mut self,
+        identity: &str,
+        group_info_bytes: &[u8],
+        ratchet_tree_bytes: &[u8],
+        config: crate::types::GroupConfig,
+    ) -> Result<(Vec<u8>, Vec<u8>), MLSError> {
+        let signer_public_key = self.signers_by_identity
+            .get(identity.as_bytes())
+            .ok_or_else(|| MLSError::group_not_found(format!("No signer for identity: {}", identity)))?
+            .clone();
+
+        let (group_info_msg, _) = MlsMessageIn::tls_deserialize_bytes(group_info_bytes)
+            .map_err(|e| MLSError::serialization_error(e))?;
+        let verifiable_group_info = match group_info_msg.extract() {
+            MlsMessageBodyIn::GroupInfo(gi) => gi,
+            _ => return Err(MLSError::invalid_input("Not a GroupInfo message")),
+        };
+
+        let (ratchet_tree, _) = RatchetTreeIn::tls_deserialize_bytes(ratchet_tree_bytes)
+            .map_err(|e| MLSError::serialization_error(e))?;
+
+        let signature_scheme = ciphersuite_for(&config.ciphersuite).signature_algorithm();
+        let signer = SignatureKeyPair::read(self.provider.storage(), &signer_public_key, signature_scheme)
+            .ok_or_else(|| MLSError::openmls_error("signer key pair not found in storage"))?;
+
+        let credential = Self::build_credential(identity, &config.credential_type)?;
+        let credential_with_key = CredentialWithKey {
+            credential,
+            signature_key: signer_public_key.clone().into(),
+        };
+
+        let join_config = MlsGroupJoinConfig::default();
+
+        let (group, commit, _group_info) = MlsGroup::join_by_external_commit(
+            &self.provider,
+            &signer,
+            Some(ratchet_tree),
+            verifiable_group_info,
+            &join_config,
+            &[],
+            credential_with_key,
+        )
+        .map_err(|e| {
+            crate::debug_log!("[MLS-CONTEXT] ERROR: join_by_external_commit failed: {:?}", e);
+            MLSError::openmls_error(e)
+        })?;
+
+        let group_id = group.group_id().as_slice().to_vec();
+
+        if let Err(e) = self.epoch_secret_manager.export_current_epoch_secret(&group, &self.provider) {
+            crate::warn_log!("[MLS-CONTEXT] WARNING: Failed to export epoch secret after external commit join: {:?}", e);
+        }
+
+        match export_resumption_psk(&group, &self.provider) {
+            Ok(secret) => self.resumption_psks.capture(&group_id, group.epoch().as_u64(), secret),
+            Err(e) => crate::warn_log!("[MLS-CONTEXT] WARNING: Failed to capture resumption PSK after external commit join: {:?}", e),
+        }
+
+        self.add_group(group, identity, config)?;
+        self.reinstate_last_resort_bundles();
+
+        let commit_bytes = commit.tls_serialize_detached()
+            .map_err(|e| MLSError::serialization_error(e))?;
+
+        Ok((group_id, commit_bytes))
+    }
+
+    /// Create a fresh group for `identity` that carries forward the
+    /// resumption PSK captured for `source_group_id`'s latest epoch, and
+    /// immediately add `members` to it, producing a Welcome
+    ///
+    /// Lets the app spin off a sub-conversation from an existing group
+    /// without the invited members re-establishing trust from scratch. The
+    /// resumption PSK (if any was captured for `source_group_id`) is bound
+    /// into the new group's key schedule in the same commit that adds
+    /// `members`, via `commit_with_resumption_psk`'s external-PSK proposal.
+    /// Branching still succeeds (with a logged warning) if no resumption PSK
+    /// was ever captured for `source_group_id`.
+    pub fn branch_group(
+        &mut self,
+        source_group_id: &[u8],
+        identity: &str,
+        members: Vec<KeyPackage>,
+        config: crate::types::GroupConfig,
+    ) -> Result<(Vec<u8>, Vec<u8>), MLSError> {
+        let resumption_psk = self.latest_resumption_psk(source_group_id);
+        match &resumption_psk {
+            Some((epoch, _)) => crate::debug_log!("[MLS-CONTEXT] branch_group: carrying forward resumption PSK from epoch {} of source group {}", epoch, hex::encode(source_group_id)),
+            None => crate::warn_log!("[MLS-CONTEXT] branch_group: no resumption PSK recorded for source group {}, branching without one", hex::encode(source_group_id)),
+        }
+
+        let new_group_id = self.create_group(identity, config)?;
+
+        if let Some((epoch, secret)) = &resumption_psk {
+            self.capture_resumption_psk(&new_group_id, *epoch, secret.clone());
+        }
+
+        if members.is_empty() {
+            return Ok((new_group_id, Vec::new()));
+        }
+
+        let gid = GroupId::from_slice(&new_group_id);
+        let welcome_bytes = self.with_group(&gid, |group, provider, signer| {
+            let (_commit, welcome) = commit_with_resumption_psk(
+                group,
+                provider,
+                signer,
+                &members,
+                resumption_psk.as_ref().map(|(_, secret)| secret.as_slice()),
+            )?;
+
+            let welcome = welcome.ok_or_else(|| MLSError::invalid_input("branch_group: commit produced no Welcome for the added members"))?;
+
+            welcome.tls_serialize_detached().map_err(|e| MLSError::serialization_error(e))
+        })?;
+
+        Ok((new_group_id, welcome_bytes))
+    }
+
+    /// Replace a wedged group with a freshly created one for `identity`,
+    /// carrying forward the resumption PSK recorded at `group_id`'s latest
+    /// epoch, and re-add `members` to it in the same commit that creates it
+    ///
+    /// Gives the app a way to recover a group that can no longer make
+    /// progress (e.g. its ratchet tree desynced beyond repair) without the
+    /// members involved re-establishing trust from scratch. See
+    /// `branch_group`'s doc comment: the resumption PSK (if any was recorded
+    /// for `group_id`) is bound into the new group's key schedule in the
+    /// same commit that re-adds `members`, via `commit_with_resumption_psk`.
+    pub fn reinit_group(
+        &mut self,
+        group_id: &[u8],
+        identity: &str,
+        members: Vec<KeyPackage>,
+        new_config: crate::types::GroupConfig,
+    ) -> Result<(Vec<u8>, Vec<u8>), MLSError> {
+        if members.is_empty() {
+            return Err(MLSError::invalid_input("reinit_group requires at least one member to re-add"));
+        }
+
+        let resumption_psk = self.latest_resumption_psk(group_id);
+        match &resumption_psk {
+            Some((epoch, _)) => crate::debug_log!("[MLS-CONTEXT] reinit_group: carrying forward resumption PSK from epoch {} of the group being replaced", epoch),
+            None => crate::warn_log!("[MLS-CONTEXT] reinit_group: no resumption PSK recorded for group {}, reiniting without one", hex::encode(group_id)),
+        }
+
+        let new_group_id = self.create_group(identity, new_config)?;
+
+        if let Some((epoch, secret)) = &resumption_psk {
+            self.capture_resumption_psk(&new_group_id, *epoch, secret.clone());
+        }
+
+        let gid = GroupId::from_slice(&new_group_id);
+        let (welcome_bytes, commit_bytes) = self.with_group(&gid, |group, provider, signer| {
+            let (commit, welcome) = commit_with_resumption_psk(
+                group,
+                provider,
+                signer,
+                &members,
+                resumption_psk.as_ref().map(|(_, secret)| secret.as_slice()),
+            )?;
+
+            let welcome = welcome.ok_or_else(|| MLSError::invalid_input("reinit_group: commit produced no Welcome for the re-added members"))?;
+
+            let welcome_bytes = welcome.tls_serialize_detached().map_err(|e| MLSError::serialization_error(e))?;
+            let commit_bytes = commit.tls_serialize_detached().map_err(|e| MLSError::serialization_error(e))?;
+            Ok((welcome_bytes, commit_bytes))
+        })?;
+
+        Ok((welcome_bytes, commit_bytes))
+    }
+
+    /// Record a staged Welcome, optionally persisting it to the durable log so
+    /// it survives an app restart before it is merged
+    pub fn store_staged_welcome(&mut self, id: String, group_id: &[u8], raw_welcome_bytes: Vec<u8>, staged: StagedWelcome) {
+        if let Some(log) = &self.staged_op_log {
+            let entry = crate::staged_registry::StagedLogEntry {
+                id: id.clone(),
+                epoch: 0, // the epoch isn't known until the Welcome is finalized into a group
+                kind: crate::staged_registry::StagedOpKind::Welcome,
+                raw_bytes: raw_welcome_bytes,
+            };
+            if let Err(e) = log.append(group_id, entry) {
+                crate::warn_log!("[MLS-CONTEXT] ‚ö†Ô∏è Failed to persist staged welcome {}: {:?}", id, e);
+            }
+        }
         self.staged_welcomes.insert(id, staged);
     }
 
-    pub fn take_staged_welcome(&mut self, id: &str) -> Result<StagedWelcome, MLSError> {
-        self.staged_welcomes.remove(id)
-            .ok_or_else(|| MLSError::invalid_input("Staged welcome not found"))
+    /// Take a staged Welcome by id, marking it complete in the durable log
+    pub fn take_staged_welcome(&mut self, id: &str, group_id: &[u8]) -> Result<StagedWelcome, MLSError> {
+        let staged = self.staged_welcomes.remove(id)
+            .ok_or_else(|| MLSError::invalid_input("Staged welcome not found"))?;
+        if let Some(log) = &self.staged_op_log {
+            if let Err(e) = log.complete(group_id, id) {
+                crate::warn_log!("[MLS-CONTEXT] ‚ö†Ô∏è Failed to clear persisted staged welcome {}: {:?}", id, e);
+            }
+        }
+        Ok(staged)
     }
 
-    pub fn store_staged_commit(&mut self, id: String, staged: Box<StagedCommit>) {
-        self.staged_commits.insert(id, staged);
+    /// Record a staged commit, optionally persisting it to the durable log so
+    /// it survives an app restart before it is merged
+    ///
+    /// Errors if a staged commit is already pending for this id (one
+    /// outstanding staged commit per group - see `store_staged_commit`'s
+    /// callers) rather than silently overwriting it: the prior entry's
+    /// membership delta would be dropped with no way for the caller to know
+    /// it never got merged or rejected, and the durable log's `complete`
+    /// later removes all entries sharing this id regardless of which one was
+    /// actually acted on.
+    pub fn store_staged_commit(
+        &mut self,
+        id: String,
+        group_id: &[u8],
+        epoch: u64,
+        raw_commit_bytes: Vec<u8>,
+        staged: Box<StagedCommit>,
+        sender_credential: crate::types::CredentialData,
+        is_external: bool,
+    ) -> Result<(), MLSError> {
+        if self.staged_commits.contains_key(&id) {
+            return Err(MLSError::invalid_input(
+                "a staged commit is already pending for this group; merge or reject it first",
+            ));
+        }
+        if let Some(log) = &self.staged_op_log {
+            let entry = crate::staged_registry::StagedLogEntry {
+                id: id.clone(),
+                epoch,
+                kind: crate::staged_registry::StagedOpKind::Commit,
+                raw_bytes: raw_commit_bytes,
+            };
+            if let Err(e) = log.append(group_id, entry) {
+                crate::warn_log!("[MLS-CONTEXT] ‚ö†Ô∏è Failed to persist staged commit {}: {:?}", id, e);
+            }
+        }
+        self.staged_commits.insert(id, StoredStagedCommit { staged, sender_credential, is_external });
+        Ok(())
     }
 
-    pub fn take_staged_commit(&mut self, id: &str) -> Result<Box<StagedCommit>, MLSError> {
-        self.staged_commits.remove(id)
-            .ok_or_else(|| MLSError::invalid_input("Staged commit not found"))
+    /// Take a staged commit by id, marking it complete in the durable log
+    pub fn take_staged_commit(&mut self, id: &str, group_id: &[u8]) -> Result<StoredStagedCommit, MLSError> {
+        let stored = self.staged_commits.remove(id)
+            .ok_or_else(|| MLSError::invalid_input("Staged commit not found"))?;
+        if let Some(log) = &self.staged_op_log {
+            if let Err(e) = log.complete(group_id, id) {
+                crate::warn_log!("[MLS-CONTEXT] ‚ö†Ô∏è Failed to clear persisted staged commit {}: {:?}", id, e);
+            }
+        }
+        Ok(stored)
+    }
+
+    /// Look up a staged commit by id without consuming it, for re-inspecting
+    /// a commit already staged via `process_message`/`stage_commit`
+    pub fn peek_staged_commit(&self, id: &str) -> Option<&StoredStagedCommit> {
+        self.staged_commits.get(id)
     }
 
     /// Check if a group exists in the context
@@ -285,12 +1595,15 @@ impl MLSContextInner {
 
     /// Export a group's state for persistent storage
     ///
-    /// Uses OpenMLS's built-in load/save mechanism.
-    /// Returns just the group ID and signer key - the group state
-    /// is persisted in OpenMLS's internal storage which is memory-based.
-    ///
-    /// NOTE: This is a simplified implementation. For true persistence,
-    /// we'd need to implement a custom StorageProvider that writes to disk.
+    /// Returns just the group ID and signer key as a compact fixed-format
+    /// blob; the actual group state (tree, transcript hash, key material) is
+    /// held by whichever `StorageProvider` is currently installed - the
+    /// default in-memory one, or a host-supplied `GroupStateStorage` callback
+    /// installed via `install_storage_provider`, which persists each write to
+    /// disk as it happens rather than needing a wholesale export/import
+    /// round-trip. This function and `import_group_state` exist only to
+    /// recover a group's id/signer-key pairing across a restart; they don't
+    /// themselves move any OpenMLS state.
     pub fn export_group_state(&self, group_id: &[u8]) -> Result<Vec<u8>, MLSError> {
         crate::debug_log!("[MLS-CONTEXT] export_group_state: Starting for group {}", hex::encode(group_id));
 
@@ -321,8 +1634,10 @@ impl MLSContextInner {
 
     /// Import a group's state from persistent storage
     ///
-    /// NOTE: This is a placeholder for the singleton approach.
-    /// Groups are already in memory, so this just validates the group exists.
+    /// Just validates that the group id encoded in `state_bytes` (as produced
+    /// by `export_group_state`) is already present in `self.groups` and
+    /// returns it; the group's actual state was already reloaded from
+    /// whichever `StorageProvider` is installed by the time this runs.
     pub fn import_group_state(&mut self, state_bytes: &[u8]) -> Result<Vec<u8>, MLSError> {
         crate::debug_log!("[MLS-CONTEXT] import_group_state: Starting with {} bytes", state_bytes.len());
 
@@ -365,9 +1680,17 @@ impl MLSContextInner {
     pub fn serialize_storage(&self) -> Result<Vec<u8>, MLSError> {
         crate::debug_log!("[MLS-CONTEXT] serialize_storage: Starting");
 
+        // Only the default in-memory backend can be serialized wholesale; once
+        // a `GroupStateStorage` callback is installed via `install_storage_provider`
+        // it's expected to persist incrementally on its own, so there's nothing
+        // for this all-or-nothing blob to capture.
+        let memory_storage = self.provider.memory_storage().ok_or_else(|| {
+            MLSError::invalid_input("serialize_storage is unavailable once a callback storage provider is installed")
+        })?;
+
         // Serialize the raw storage
         let mut storage_buffer = Vec::new();
-        self.provider.storage()
+        memory_storage
             .serialize(&mut storage_buffer)
             .map_err(|e| {
                 crate::debug_log!("[MLS-CONTEXT] ERROR: Failed to serialize storage: {:?}", e);
@@ -381,6 +1704,12 @@ impl MLSContextInner {
             .map(|(group_id, state)| GroupMetadata {
                 group_id: group_id.clone(),
                 signer_public_key: state.signer_public_key.clone(),
+                max_past_epochs: state.config.max_past_epochs,
+                out_of_order_tolerance: state.config.out_of_order_tolerance,
+                maximum_forward_distance: state.config.maximum_forward_distance,
+                ciphersuite: state.config.ciphersuite,
+                credential_type: state.config.credential_type.clone(),
+                wire_format_policy: state.config.wire_format_policy.clone(),
             })
             .collect();
 
@@ -434,9 +1763,16 @@ impl MLSContextInner {
                 }
             }
 
+            let content_hash = content_hash_for_bundle(&self.provider, bundle)?;
+            let (identity, ciphersuite, credential_type) = self.bundle_provenance(bundle);
+
             serialized_bundles.push(SerializedKeyPackageBundle {
                 hash_ref: hash_ref.clone(),
                 bundle_bytes: Vec::new(), // Bundles are in storage_bytes via provider, not duplicated here
+                content_hash,
+                identity,
+                ciphersuite,
+                credential_type,
             });
         }
         crate::debug_log!("[MLS-CONTEXT] Recorded {} key package bundle references", serialized_bundles.len());
@@ -447,6 +1783,10 @@ impl MLSContextInner {
             group_metadata,
             signers_by_identity: signers_by_identity_hex,
             key_package_bundles: serialized_bundles,
+            key_package_history: self.key_package_history.clone(),
+            rotation_counter: self.rotation_counter,
+            last_resort_hash_refs: self.last_resort_bundles.iter().cloned().collect(),
+            schema_version: CURRENT_SCHEMA_VERSION,
         };
 
         // Serialize to JSON
@@ -471,11 +1811,36 @@ impl MLSContextInner {
     ///
     /// NOTE: This replaces the entire storage, so it should only be called
     /// during initialization, not after groups are already created.
-    pub fn deserialize_storage(&mut self, json_bytes: &[u8]) -> Result<(), MLSError> {
+    ///
+    /// Self-heals missing key package bundles instead of just logging them:
+    /// for every bundle that failed to restore and whose identity was recorded
+    /// at serialize time, mints a fresh replacement with the same ciphersuite
+    /// and credential type (see
+    /// `regenerate_key_package_bundle`) and reports it in the returned
+    /// summary so the caller can re-publish it, closing the
+    /// `NoMatchingKeyPackage` window without a round trip back to the host.
+    pub fn deserialize_storage(&mut self, json_bytes: &[u8]) -> Result<crate::types::BundleRestorationSummary, MLSError> {
         crate::debug_log!("[MLS-CONTEXT] deserialize_storage: Starting with {} bytes", json_bytes.len());
 
-        // Deserialize the JSON state
-        let serialized_state: SerializedState = serde_json::from_slice(json_bytes)
+        // Parse as a raw value first so `migrate_serialized_state` can bring an
+        // older blob's shape up to `CURRENT_SCHEMA_VERSION` before it's read
+        // into the typed `SerializedState`
+        let raw_state: serde_json::Value = serde_json::from_slice(json_bytes)
+            .map_err(|e| {
+                crate::debug_log!("[MLS-CONTEXT] ERROR: Failed to parse JSON: {:?}", e);
+                MLSError::invalid_input(format!("JSON parsing failed: {}", e))
+            })?;
+
+        let stored_schema_version = raw_state
+            .get("schema_version")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0) as u32;
+
+        crate::debug_log!("[MLS-CONTEXT] Stored schema_version: {}, current: {}", stored_schema_version, CURRENT_SCHEMA_VERSION);
+
+        let migrated_state = migrate_serialized_state(raw_state, stored_schema_version)?;
+
+        let serialized_state: SerializedState = serde_json::from_value(migrated_state)
             .map_err(|e| {
                 crate::debug_log!("[MLS-CONTEXT] ERROR: Failed to deserialize JSON: {:?}", e);
                 MLSError::invalid_input(format!("JSON deserialization failed: {}", e))
@@ -483,6 +1848,12 @@ impl MLSContextInner {
 
         crate::debug_log!("[MLS-CONTEXT] Deserialized {} groups metadata", serialized_state.group_metadata.len());
 
+        // Only the default in-memory backend supports wholesale restore; see
+        // the matching guard in `serialize_storage`.
+        let memory_storage = self.provider.memory_storage().ok_or_else(|| {
+            MLSError::invalid_input("deserialize_storage is unavailable once a callback storage provider is installed")
+        })?;
+
         // Deserialize the raw storage
         use std::io::Cursor;
         let mut cursor = Cursor::new(&serialized_state.storage_bytes);
@@ -494,7 +1865,7 @@ impl MLSContextInner {
             })?;
 
         // Replace the HashMap in the existing storage
-        let mut current_values = self.provider.storage().values.write().unwrap();
+        let mut current_values = memory_storage.values.write().unwrap();
         let loaded_values = loaded_storage.values.read().unwrap();
 
         current_values.clear();
@@ -516,6 +1887,14 @@ impl MLSContextInner {
                     self.groups.insert(group_id_bytes, GroupState {
                         group,
                         signer_public_key: metadata.signer_public_key,
+                        config: crate::types::GroupConfig {
+                            max_past_epochs: metadata.max_past_epochs,
+                            out_of_order_tolerance: metadata.out_of_order_tolerance,
+                            maximum_forward_distance: metadata.maximum_forward_distance,
+                            credential_type: metadata.credential_type,
+                            wire_format_policy: metadata.wire_format_policy,
+                            ciphersuite: metadata.ciphersuite,
+                        },
                     });
                 }
                 Ok(None) => {
@@ -546,6 +1925,11 @@ impl MLSContextInner {
         }
         crate::debug_log!("[MLS-CONTEXT] Restored {} identity mappings", self.signers_by_identity.len());
 
+        self.key_package_history = serialized_state.key_package_history;
+        self.rotation_counter = serialized_state.rotation_counter;
+        crate::debug_log!("[MLS-CONTEXT] Restored {} key package history entries (rotation counter={})",
+            self.key_package_history.len(), self.rotation_counter);
+
         // CRITICAL: Restore key package bundles from provider storage
         // After deserialization, the key package bundles are in the provider storage
         // We need to rebuild the cache HashMap by iterating through the saved hash_refs
@@ -561,6 +1945,12 @@ impl MLSContextInner {
 
         let mut _restored_count = 0;
         let mut missing_count = 0;
+        let mut corrupt_count = 0;
+        // Which identities actually had a bundle go missing, with the
+        // ciphersuite/credential type to regenerate it with - as opposed to
+        // every identity this context knows a signer for, most of which
+        // restored their bundle just fine
+        let mut missing_bundles: Vec<(String, crate::types::CiphersuiteSelector, crate::types::CredentialTypeSelector)> = Vec::new();
 
         for (i, serialized_bundle) in serialized_state.key_package_bundles.iter().enumerate() {
             // The hash_ref bytes are the raw bytes returned by hash_ref.as_slice()
@@ -572,10 +1962,24 @@ impl MLSContextInner {
             // Bundles were stored in provider storage during serialization (via bundle.store())
             match self.provider.storage().key_package::<HashReference, KeyPackageBundle>(&hash_ref_value) {
                 Ok(Some(bundle)) => {
-                    self.key_package_bundles.insert(serialized_bundle.hash_ref.clone(), bundle);
-                    crate::debug_log!("[MLS-CONTEXT]   ✅ Restored bundle {}: hash_ref={}",
-                        i, hex::encode(&serialized_bundle.hash_ref));
-                    _restored_count += 1;
+                    // An empty manifest entry means this blob predates the
+                    // integrity manifest - nothing to check it against, so
+                    // trust it the way `deserialize_storage` always used to
+                    let manifest_ok = serialized_bundle.content_hash.is_empty()
+                        || content_hash_for_bundle(&self.provider, &bundle)
+                            .map(|actual| actual == serialized_bundle.content_hash)
+                            .unwrap_or(false);
+
+                    if manifest_ok {
+                        self.key_package_bundles.insert(serialized_bundle.hash_ref.clone(), bundle);
+                        crate::debug_log!("[MLS-CONTEXT]   ✅ Restored bundle {}: hash_ref={}",
+                            i, hex::encode(&serialized_bundle.hash_ref));
+                        _restored_count += 1;
+                    } else {
+                        crate::warn_log!("[MLS-CONTEXT]   ⚠️ Bundle {} FAILED integrity check (hash_ref={}): content hash doesn't match the manifest recorded at serialize time",
+                            i, hex::encode(&serialized_bundle.hash_ref));
+                        corrupt_count += 1;
+                    }
                 }
                 Ok(None) => {
                     crate::debug_log!("[MLS-CONTEXT]   ❌ Bundle {} NOT FOUND in storage (hash_ref={})",
@@ -586,10 +1990,47 @@ impl MLSContextInner {
                     crate::debug_log!("[MLS-CONTEXT]        2. Storage corruption occurred");
                     crate::debug_log!("[MLS-CONTEXT]        3. Bundle was removed from storage before deserialization");
                     missing_count += 1;
+                    if !serialized_bundle.identity.is_empty() {
+                        missing_bundles.push((
+                            serialized_bundle.identity.clone(),
+                            serialized_bundle.ciphersuite,
+                            serialized_bundle.credential_type.clone(),
+                        ));
+                    }
                 }
                 Err(e) => {
                     crate::debug_log!("[MLS-CONTEXT]   ❌ ERROR: Failed to query storage for bundle {}: {:?}", i, e);
                     missing_count += 1;
+                    if !serialized_bundle.identity.is_empty() {
+                        missing_bundles.push((
+                            serialized_bundle.identity.clone(),
+                            serialized_bundle.ciphersuite,
+                            serialized_bundle.credential_type.clone(),
+                        ));
+                    }
+                }
+            }
+        }
+
+        // Dedicated restore pass for last-resort bundles, tracked via their
+        // own hash_ref list rather than `serialized_state.key_package_bundles`
+        // so a device that's exhausted every regular bundle - the one case
+        // that list being empty actually matters for - still gets its
+        // last-resort bundle back and can keep being added to groups
+        self.last_resort_bundles.clear();
+        for hash_ref in &serialized_state.last_resort_hash_refs {
+            let hash_ref_value = HashReference::from_slice(hash_ref);
+            match self.provider.storage().key_package::<HashReference, KeyPackageBundle>(&hash_ref_value) {
+                Ok(Some(bundle)) => {
+                    self.key_package_bundles.entry(hash_ref.clone()).or_insert(bundle);
+                    self.last_resort_bundles.insert(hash_ref.clone());
+                    crate::debug_log!("[MLS-CONTEXT]   ✅ Restored last-resort bundle: hash_ref={}", hex::encode(hash_ref));
+                }
+                Ok(None) => {
+                    crate::warn_log!("[MLS-CONTEXT]   ⚠️ Last-resort bundle NOT FOUND in storage (hash_ref={})", hex::encode(hash_ref));
+                }
+                Err(e) => {
+                    crate::warn_log!("[MLS-CONTEXT]   ⚠️ Failed to query storage for last-resort bundle {}: {:?}", hex::encode(hash_ref), e);
                 }
             }
         }
@@ -601,11 +2042,35 @@ impl MLSContextInner {
         crate::debug_log!("[MLS-CONTEXT]   - Expected bundles: {}", expected_count);
         crate::debug_log!("[MLS-CONTEXT]   - Restored bundles: {}", restored_count);
         crate::debug_log!("[MLS-CONTEXT]   - Missing bundles:  {}", missing_count);
+        crate::debug_log!("[MLS-CONTEXT]   - Corrupt bundles:  {}", corrupt_count);
+
+        let mut regenerated = Vec::new();
 
         if missing_count > 0 {
             crate::debug_log!("[MLS-CONTEXT] ⚠️ WARNING: {} key package bundles were missing from storage!", missing_count);
-            crate::debug_log!("[MLS-CONTEXT]   This will cause NoMatchingKeyPackage errors for pending Welcome messages");
-            crate::debug_log!("[MLS-CONTEXT]   IMMEDIATE ACTION REQUIRED: Force-create bundles in Swift layer (ensureLocalKeyPackageBundles)");
+            crate::debug_log!("[MLS-CONTEXT]   Self-healing: regenerating a replacement for the {} identity/identities actually affected",
+                missing_bundles.len());
+
+            for (identity, ciphersuite, credential_type) in missing_bundles {
+                match self.regenerate_key_package_bundle(&identity, &ciphersuite, &credential_type, false) {
+                    Ok(regenerated_bundle) => {
+                        crate::debug_log!("[MLS-CONTEXT]   ✅ Regenerated bundle for identity '{}' (hash_ref={})",
+                            identity, hex::encode(&regenerated_bundle.hash_ref));
+                        regenerated.push(regenerated_bundle);
+                    }
+                    Err(e) => {
+                        crate::warn_log!("[MLS-CONTEXT]   ⚠️ Failed to regenerate bundle for identity '{}': {:?}", identity, e);
+                    }
+                }
+            }
+        } else if corrupt_count > 0 {
+            // Deliberately not self-healed the way a missing bundle is:
+            // storage returned *something* for this hash_ref, just not what
+            // was written, which smells more like a backend bug or a
+            // mid-write crash than "never synced" - better to surface it and
+            // let the caller decide whether to regenerate or re-sync from
+            // the host instead of quietly minting a replacement
+            crate::warn_log!("[MLS-CONTEXT] ⚠️ WARNING: {} key package bundles failed their integrity check and were excluded from the cache!", corrupt_count);
         } else if expected_count > 0 {
             crate::debug_log!("[MLS-CONTEXT] ✅ All {} key package bundles restored successfully - Welcome processing ready", restored_count);
         } else {
@@ -613,6 +2078,11 @@ impl MLSContextInner {
         }
 
         crate::debug_log!("[MLS-CONTEXT] deserialize_storage: Complete");
-        Ok(())
+        Ok(crate::types::BundleRestorationSummary {
+            restored_count: restored_count as u32,
+            missing_count: missing_count as u32,
+            corrupt_count: corrupt_count as u32,
+            regenerated,
+        })
     }
 }
@@ -0,0 +1,52 @@
+// resumption_psk.rs
+//
+// In-process store for the resumption PSKs captured at group creation, on
+// joins and on commit merges, so `branch_group`/`reinit_group` have something to carry
+// forward from a group's prior epoch when spinning off a sub-conversation or
+// recovering a wedged group. Unlike epoch secrets (which may need to survive
+// an app restart to decrypt delayed messages, and so go through the
+// Swift-backed `EpochSecretStorage` callback), a resumption PSK is only ever
+// consumed synchronously by `branch_group`/`reinit_group` within the same
+// process that captured it, so a plain in-memory map is enough here.
+
+use std::collections::HashMap;
+
+/// Resumption PSKs captured per `(group_id, epoch)`
+#[derive(Default)]
+pub struct ResumptionPskStore {
+    secrets: HashMap<(Vec<u8>, u64), Vec<u8>>,
+}
+
+impl ResumptionPskStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the PSK captured for `group_id` at `epoch`, overwriting any
+    /// previous value for the same pair
+    pub fn capture(&mut self, group_id: &[u8], epoch: u64, secret: Vec<u8>) {
+        self.secrets.insert((group_id.to_vec(), epoch), secret);
+    }
+
+    /// The PSK captured for `group_id` at exactly `epoch`, if any
+    pub fn get(&self, group_id: &[u8], epoch: u64) -> Option<&Vec<u8>> {
+        self.secrets.get(&(group_id.to_vec(), epoch))
+    }
+
+    /// The most recently captured `(epoch, secret)` pair for `group_id` - the
+    /// one `branch_group`/`reinit_group` fall back to when no specific epoch
+    /// is requested
+    pub fn latest(&self, group_id: &[u8]) -> Option<(u64, &Vec<u8>)> {
+        self.secrets
+            .iter()
+            .filter(|((gid, _), _)| gid.as_slice() == group_id)
+            .map(|((_, epoch), secret)| (*epoch, secret))
+            .max_by_key(|(epoch, _)| *epoch)
+    }
+
+    /// Drop every PSK recorded for `group_id` strictly older than `before_epoch`,
+    /// mirroring `EpochSecretManager::prune_epoch_secrets`
+    pub fn prune_before(&mut self, group_id: &[u8], before_epoch: u64) {
+        self.secrets.retain(|(gid, epoch), _| gid.as_slice() != group_id || *epoch >= before_epoch);
+    }
+}
@@ -0,0 +1,140 @@
+// staged_registry.rs
+//
+// Durable, checkpointed registry of staged (not-yet-merged) Welcomes and Commits.
+//
+// `MLSContextInner` otherwise keeps staged Welcomes/Commits only in its in-memory
+// maps; if the app is killed between receiving a commit/Welcome and merging it,
+// that pending state is lost and the group can desync. This module persists each
+// staged operation as an append-only log entry through the `GroupStateStorage`
+// callback, with periodic full checkpoints (in the style of Bayou-style op logs)
+// that fold the tail into a single blob; a cold start reads back the last
+// checkpoint plus the tail written since, i.e. every not-yet-completed entry.
+
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+
+use crate::error::MLSError;
+use crate::types::{GroupStateStorage, GroupStorageEntityType};
+
+/// How many appended operations accumulate in the tail before it is folded
+/// into a full checkpoint
+const CHECKPOINT_INTERVAL: usize = 20;
+
+/// Fixed storage keys the checkpoint/tail blobs are written under; there is
+/// exactly one of each per group, so no per-entry key is needed
+const CHECKPOINT_KEY: &[u8] = b"checkpoint";
+const TAIL_KEY: &[u8] = b"tail";
+
+#[derive(Clone, Serialize, Deserialize, uniffi::Enum)]
+pub enum StagedOpKind {
+    Welcome,
+    Commit,
+}
+
+/// One entry in the append-only staged-operation log
+///
+/// Rather than attempting to serialize OpenMLS's `StagedWelcome`/`StagedCommit`
+/// types directly (they carry borrowed provider state and are not designed to
+/// be persisted), each entry retains the raw Welcome/commit message bytes and
+/// is replayed through OpenMLS's normal parsing path (`StagedWelcome::new_from_welcome`
+/// / `MlsGroup::process_message`) when rehydrated.
+#[derive(Clone, Serialize, Deserialize, uniffi::Record)]
+pub struct StagedLogEntry {
+    pub id: String,
+    pub epoch: u64,
+    pub kind: StagedOpKind,
+    pub raw_bytes: Vec<u8>,
+}
+
+/// A full snapshot of all staged operations recorded for a group as of the
+/// last checkpoint
+#[derive(Clone, Serialize, Deserialize, Default)]
+struct StagedCheckpoint {
+    entries: Vec<StagedLogEntry>,
+}
+
+/// Append-only, checkpointed log of staged welcomes/commits for every group,
+/// backed by a `GroupStateStorage` callback
+pub struct StagedOpLog {
+    storage: Arc<dyn GroupStateStorage>,
+}
+
+impl StagedOpLog {
+    pub fn new(storage: Arc<dyn GroupStateStorage>) -> Self {
+        Self { storage }
+    }
+
+    fn load_checkpoint(&self, group_id: &[u8]) -> Result<StagedCheckpoint, MLSError> {
+        match self.storage.read(group_id.to_vec(), GroupStorageEntityType::StagedOperationCheckpoint, CHECKPOINT_KEY.to_vec()) {
+            Some(bytes) => serde_json::from_slice(&bytes).map_err(MLSError::serialization_error),
+            None => Ok(StagedCheckpoint::default()),
+        }
+    }
+
+    fn write_checkpoint(&self, group_id: &[u8], checkpoint: &StagedCheckpoint) -> Result<(), MLSError> {
+        let bytes = serde_json::to_vec(checkpoint).map_err(MLSError::serialization_error)?;
+        self.storage
+            .write(group_id.to_vec(), GroupStorageEntityType::StagedOperationCheckpoint, CHECKPOINT_KEY.to_vec(), bytes)
+            .map_err(MLSError::storage_error)
+    }
+
+    fn load_tail(&self, group_id: &[u8]) -> Result<Vec<StagedLogEntry>, MLSError> {
+        match self.storage.read(group_id.to_vec(), GroupStorageEntityType::StagedOperationTail, TAIL_KEY.to_vec()) {
+            Some(bytes) => serde_json::from_slice(&bytes).map_err(MLSError::serialization_error),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn write_tail(&self, group_id: &[u8], tail: &[StagedLogEntry]) -> Result<(), MLSError> {
+        let bytes = serde_json::to_vec(tail).map_err(MLSError::serialization_error)?;
+        self.storage
+            .write(group_id.to_vec(), GroupStorageEntityType::StagedOperationTail, TAIL_KEY.to_vec(), bytes)
+            .map_err(MLSError::storage_error)
+    }
+
+    /// Append a staged operation to the log, folding the tail into a new
+    /// checkpoint once it grows past `CHECKPOINT_INTERVAL`
+    pub fn append(&self, group_id: &[u8], entry: StagedLogEntry) -> Result<(), MLSError> {
+        let mut tail = self.load_tail(group_id)?;
+        tail.push(entry);
+
+        if tail.len() >= CHECKPOINT_INTERVAL {
+            let mut checkpoint = self.load_checkpoint(group_id)?;
+            checkpoint.entries.extend(tail.drain(..));
+            self.write_checkpoint(group_id, &checkpoint)?;
+            self.write_tail(group_id, &tail)?; // tail is now empty
+        } else {
+            self.write_tail(group_id, &tail)?;
+        }
+
+        Ok(())
+    }
+
+    /// Replay every not-yet-completed staged operation for a group: the last
+    /// checkpoint plus whatever has accumulated in the tail since
+    pub fn replay(&self, group_id: &[u8]) -> Result<Vec<StagedLogEntry>, MLSError> {
+        let mut entries = self.load_checkpoint(group_id)?.entries;
+        entries.extend(self.load_tail(group_id)?);
+        Ok(entries)
+    }
+
+    /// Remove a staged operation from both the checkpoint and the tail once
+    /// it has been merged or rejected, so the log doesn't grow unbounded
+    pub fn complete(&self, group_id: &[u8], id: &str) -> Result<(), MLSError> {
+        let mut checkpoint = self.load_checkpoint(group_id)?;
+        let before = checkpoint.entries.len();
+        checkpoint.entries.retain(|e| e.id != id);
+        if checkpoint.entries.len() != before {
+            self.write_checkpoint(group_id, &checkpoint)?;
+        }
+
+        let mut tail = self.load_tail(group_id)?;
+        let before = tail.len();
+        tail.retain(|e| e.id != id);
+        if tail.len() != before {
+            self.write_tail(group_id, &tail)?;
+        }
+
+        Ok(())
+    }
+}
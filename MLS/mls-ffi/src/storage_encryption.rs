@@ -0,0 +1,66 @@
+// storage_encryption.rs
+//
+// AEAD envelope around the JSON blob `serialize_storage` produces, so the
+// bytes a host app hands to Core Data/Keychain/iCloud backup don't carry
+// every group's ratchet secrets, exporter secrets, and signature private
+// keys in plaintext.
+//
+// Layout: [magic: 4 bytes][version: u8][nonce: 12 bytes][ciphertext || tag]
+// A fresh random nonce is generated per call, so the same plaintext never
+// produces the same ciphertext twice.
+
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+use crate::error::MLSError;
+
+const MAGIC: &[u8; 4] = b"CTBR";
+const VERSION: u8 = 1;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = MAGIC.len() + 1 + NONCE_LEN;
+
+/// Encrypt `plaintext` under `master_key`, prepending the versioned header
+pub fn seal(master_key: &[u8; 32], plaintext: &[u8]) -> Result<Vec<u8>, MLSError> {
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(master_key));
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|e| MLSError::storage_error(e).context("encrypting storage blob"))?;
+
+    let mut sealed = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+    sealed.extend_from_slice(MAGIC);
+    sealed.push(VERSION);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+/// Validate the header and AEAD-decrypt `sealed` back into the original plaintext
+///
+/// Fails with `MLSError::StorageDecryptionFailed` specifically on tag
+/// verification failure, so tampering or a wrong `master_key` is
+/// distinguishable from a malformed/truncated blob.
+pub fn open(master_key: &[u8; 32], sealed: &[u8]) -> Result<Vec<u8>, MLSError> {
+    if sealed.len() < HEADER_LEN {
+        return Err(MLSError::invalid_input("Encrypted storage blob is shorter than its header"));
+    }
+
+    let (magic, rest) = sealed.split_at(MAGIC.len());
+    let (version, rest) = rest.split_at(1);
+    let (nonce_bytes, ciphertext) = rest.split_at(NONCE_LEN);
+
+    if magic != MAGIC {
+        return Err(MLSError::invalid_input("Encrypted storage blob has an unrecognized magic header"));
+    }
+    if version[0] != VERSION {
+        return Err(MLSError::invalid_input(format!("Unsupported encrypted storage blob version: {}", version[0])));
+    }
+
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(master_key));
+    let nonce = Nonce::from_slice(nonce_bytes);
+
+    cipher
+        .decrypt(nonce, ciphertext)
+        .map_err(|_| MLSError::StorageDecryptionFailed)
+}
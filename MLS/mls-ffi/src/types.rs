@@ -26,17 +26,135 @@ pub struct DecryptResult {
     pub plaintext: Vec<u8>,
 }
 
+/// Per-message result of a `decrypt_batch` call, so one undecryptable
+/// ciphertext doesn't fail the whole batch
+#[derive(uniffi::Enum)]
+pub enum DecryptOutcome {
+    Success { plaintext: Vec<u8> },
+    Failure { message: String },
+}
+
 #[derive(uniffi::Record)]
 pub struct KeyPackageResult {
     pub key_package_data: Vec<u8>,
     pub hash_ref: Vec<u8>,
 }
 
+/// Result of `heal_key_package_desync`
+#[derive(uniffi::Enum, Clone)]
+pub enum HealOutcome {
+    /// The expected bundle was found in the cache and no further action from
+    /// the caller is needed
+    Recovered,
+    /// A fresh key package was regenerated and cached, but remote members may
+    /// still hold a reference to the stale one and need to be made to re-add
+    /// this client using the new key package
+    NeedsReAdd { stale_members: Vec<String> },
+    /// The desync could not be resolved locally (e.g. nothing was recorded
+    /// for this `convo_id`, so there is nothing to heal)
+    Unrecoverable,
+}
+
+#[derive(uniffi::Record)]
+pub struct KeyPackageBundleInfo {
+    pub hash_ref: Vec<u8>,
+    /// True if this bundle was created with the `last_resort` extension and
+    /// is kept (not deleted) across Welcome processing
+    pub last_resort: bool,
+}
+
+/// A key package bundle `deserialize_storage` regenerated locally because the
+/// one it was expecting to find in storage was missing
+#[derive(uniffi::Record)]
+pub struct RegeneratedKeyPackage {
+    pub identity: String,
+    pub hash_ref: Vec<u8>,
+    pub key_package_data: Vec<u8>,
+}
+
+/// Summary of `deserialize_storage`'s key package bundle restoration pass
+#[derive(uniffi::Record)]
+pub struct BundleRestorationSummary {
+    pub restored_count: u32,
+    pub missing_count: u32,
+    /// Bundles found in storage but whose content hash didn't match the
+    /// manifest recorded at serialize time - distinct from `missing_count`
+    /// (storage corruption vs. the entry never having been there at all).
+    /// Excluded from the cache just like a missing bundle
+    pub corrupt_count: u32,
+    /// Freshly minted bundles that replace ones `missing_count` counted as
+    /// gone; the caller should publish each one's `key_package_data` so peers
+    /// stop referencing the stale hash_ref
+    pub regenerated: Vec<RegeneratedKeyPackage>,
+}
+
+/// How long a retired key package bundle is kept resolvable after
+/// `rotate_key_packages` replaces it, before
+/// `garbage_collect_retired_key_packages` deletes it for good
+#[derive(uniffi::Record, Clone, Copy)]
+pub struct KeyPackageRetentionPolicy {
+    /// Keep a retired bundle until at least this many newer rotations have
+    /// happened for the same identity
+    pub min_rotations: u32,
+    /// ...and until at least this many seconds have passed since it was
+    /// minted. A retired bundle is only garbage-collected once BOTH
+    /// thresholds are satisfied, so a slow rotator doesn't lose history it
+    /// still needs and a fast rotator doesn't keep it forever
+    pub min_age_seconds: u64,
+}
+
+impl Default for KeyPackageRetentionPolicy {
+    fn default() -> Self {
+        Self {
+            min_rotations: 3,
+            min_age_seconds: 7 * 24 * 60 * 60, // 7 days
+        }
+    }
+}
+
+/// Result of `rotate_key_packages`
+#[derive(uniffi::Record)]
+pub struct KeyPackageRotationResult {
+    pub key_package_data: Vec<u8>,
+    pub hash_ref: Vec<u8>,
+    /// hash_refs of bundles for this identity that were garbage-collected
+    /// (past their retention window) as part of this rotation
+    pub garbage_collected: Vec<Vec<u8>>,
+}
+
+/// Result of a batched multi-member commit (`add_members_batch`/`remove_members_batch`)
+#[derive(uniffi::Record)]
+pub struct CommitBundle {
+    pub commit_data: Vec<u8>,
+    /// Present when the commit added members; always `None` for a remove-only commit
+    pub welcome_data: Option<Vec<u8>>,
+    /// The commit's `GroupInfo`, when OpenMLS produced one (e.g. the group has
+    /// the ratchet tree extension enabled), needed for external commits
+    pub group_info_data: Option<Vec<u8>>,
+}
+
 #[derive(uniffi::Record)]
 pub struct WelcomeResult {
     pub group_id: Vec<u8>,
 }
 
+/// Result of `join_group_by_external_commit`
+#[derive(uniffi::Record)]
+pub struct ExternalCommitJoinResult {
+    pub group_id: Vec<u8>,
+    /// The external commit message; broadcast this so existing members merge
+    /// it and learn of the new member
+    pub commit_data: Vec<u8>,
+}
+
+/// Result of `branch_group`
+#[derive(uniffi::Record)]
+pub struct BranchGroupResult {
+    pub new_group_id: Vec<u8>,
+    /// Welcome message for the members added to the new group; empty if none were given
+    pub welcome_data: Vec<u8>,
+}
+
 #[derive(uniffi::Record)]
 pub struct ExportedSecret {
     pub secret: Vec<u8>,
@@ -55,6 +173,7 @@ pub struct CredentialData {
 
 #[derive(uniffi::Record)]
 pub struct MemberCredential {
+    pub leaf_index: u32,
     pub credential: CredentialData,
     pub signature_key: Vec<u8>,
 }
@@ -62,17 +181,51 @@ pub struct MemberCredential {
 #[derive(uniffi::Record)]
 pub struct StagedWelcomeInfo {
     pub group_id: Vec<u8>,
+    /// IANA ciphersuite identifier the group was created with
+    pub ciphersuite: u16,
+    /// IANA protocol version identifier the group was created with
+    pub protocol_version: u16,
+    /// Epoch the group will be at once this Welcome is joined
+    pub epoch: u64,
     pub sender_credential: CredentialData,
     pub member_credentials: Vec<MemberCredential>,
+    /// hash_ref of the cached `key_package_bundle` this Welcome's encrypted
+    /// group secrets were decrypted with, if one of our own bundles matched
+    pub matched_key_package_hash_ref: Option<Vec<u8>>,
     pub staged_welcome_id: String,
 }
 
+#[derive(uniffi::Record)]
+pub struct StagedAddedMember {
+    /// The final tree leaf index isn't assigned until the commit is merged,
+    /// so the key package hash ref is this member's only stable pre-merge
+    /// identifier
+    pub credential: CredentialData,
+    pub signature_key: Vec<u8>,
+    pub key_package_ref: Vec<u8>,
+}
+
 #[derive(uniffi::Record)]
 pub struct StagedCommitInfo {
     pub group_id: Vec<u8>,
     pub sender_credential: CredentialData,
-    pub added_members: Vec<MemberCredential>,
+    /// True if the committer is not an existing group member (an external
+    /// commit or a NewMemberCommit join-by-external-commit), as opposed to
+    /// a regular member-authored commit
+    pub is_external: bool,
+    pub added_members: Vec<StagedAddedMember>,
     pub removed_members: Vec<MemberCredential>,
+    pub updated_members: Vec<UpdateProposalInfo>,
+    /// Debug-formatted proposed `GroupContextExtensions` changes, if any
+    pub extension_changes: Vec<String>,
+    /// Debug-formatted proposed `PreSharedKeyProposal`s, if any
+    pub psk_proposals: Vec<String>,
+    /// True if one of `removed_members` is this client's own leaf
+    pub self_removed: bool,
+    /// True if the commit carries a ReInit proposal
+    pub requires_reinit: bool,
+    /// Epoch the group will be at once this commit is merged
+    pub new_epoch: u64,
     pub staged_commit_id: String,
 }
 
@@ -127,7 +280,7 @@ pub enum ProposalInfo {
 pub enum ProcessedContent {
     ApplicationMessage { plaintext: Vec<u8>, sender: CredentialData },
     Proposal { proposal: ProposalInfo, proposal_ref: ProposalRef },
-    StagedCommit { new_epoch: u64 },
+    StagedCommit { info: StagedCommitInfo },
 }
 
 #[derive(uniffi::Record)]
@@ -136,11 +289,69 @@ pub struct ProcessCommitResult {
     pub update_proposals: Vec<UpdateProposalInfo>,
 }
 
-#[derive(uniffi::Record)]
+/// Selects which credential type a group/key package is created with
+#[derive(uniffi::Enum, Clone, serde::Serialize, serde::Deserialize)]
+pub enum CredentialTypeSelector {
+    /// A bare identity string, as used throughout the rest of this crate today
+    Basic,
+    /// An X.509 certificate chain (DER-encoded, leaf-first) backing the identity
+    X509 { cert_chain: Vec<Vec<u8>> },
+}
+
+impl Default for CredentialTypeSelector {
+    fn default() -> Self {
+        Self::Basic
+    }
+}
+
+/// Selects which IANA-registered MLS ciphersuite a `KeyPackage` is built with;
+/// the matching signature scheme is derived from the suite rather than chosen
+/// separately
+#[derive(uniffi::Enum, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub enum CiphersuiteSelector {
+    /// `MLS_128_DHKEMX25519_AES128GCM_SHA256_Ed25519`, the prior hardcoded suite
+    X25519Aes128Sha256Ed25519,
+    /// `MLS_128_DHKEMP256_AES128GCM_SHA256_P256`
+    P256Aes128Sha256P256,
+    /// `MLS_128_DHKEMX25519_CHACHA20POLY1305_SHA256_Ed25519`
+    X25519Chacha20Sha256Ed25519,
+    /// `MLS_256_DHKEMX448_AES256GCM_SHA512_Ed448`
+    X448Aes256Sha512Ed448,
+    /// `MLS_256_DHKEMP521_AES256GCM_SHA512_P521`
+    P521Aes256Sha512P521,
+}
+
+impl Default for CiphersuiteSelector {
+    fn default() -> Self {
+        Self::X25519Aes128Sha256Ed25519
+    }
+}
+
+/// Selects which of OpenMLS's standard `WireFormatPolicy`s a group uses for
+/// its handshake messages (commits/proposals). Application messages are
+/// always ciphertext regardless of this setting.
+#[derive(uniffi::Enum, Clone, serde::Serialize, serde::Deserialize)]
+pub enum WireFormatPolicySelector {
+    /// Handshake messages are always ciphertext (the prior hardcoded behavior)
+    PureCiphertext,
+    /// Handshake messages are always plaintext
+    PurePlaintext,
+    /// Outgoing handshake messages are plaintext; incoming ones are accepted as
+    /// either plaintext or ciphertext
+    Mixed,
+}
+
+#[derive(uniffi::Record, Clone)]
 pub struct GroupConfig {
     pub max_past_epochs: u32,
     pub out_of_order_tolerance: u32,
     pub maximum_forward_distance: u32,
+    pub credential_type: CredentialTypeSelector,
+    pub wire_format_policy: WireFormatPolicySelector,
+    /// Which MLS ciphersuite (and its derived signature scheme) this group is
+    /// created with; peers/servers that mandate a specific suite need this
+    /// set accordingly rather than relying on the prior hardcoded default
+    pub ciphersuite: CiphersuiteSelector,
 }
 
 impl Default for GroupConfig {
@@ -149,6 +360,9 @@ impl Default for GroupConfig {
             max_past_epochs: 5,  // Retain 5 past epochs to handle network delays and message reordering
             out_of_order_tolerance: 10,
             maximum_forward_distance: 2000,
+            credential_type: CredentialTypeSelector::Basic,
+            wire_format_policy: WireFormatPolicySelector::PureCiphertext,
+            ciphersuite: CiphersuiteSelector::default(),
         }
     }
 }
@@ -162,6 +376,50 @@ pub trait MLSLogger: Send + Sync {
     fn log(&self, level: String, message: String);
 }
 
+/// Entity kind stored under a group's OpenMLS storage namespace
+///
+/// Mirrors the distinct key/value spaces `openmls_traits::storage::StorageProvider`
+/// keeps for a group, so a single callback can multiplex all of them by label
+/// instead of exposing one method per entity.
+#[derive(uniffi::Enum, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GroupStorageEntityType {
+    GroupConfig,
+    Tree,
+    InterimTranscriptHash,
+    ContextGroupContext,
+    ConfirmationTag,
+    OwnLeafNodes,
+    ProposalQueue,
+    KeyPackage,
+    PskBundle,
+    EncryptionKeyPair,
+    SignatureKeyPair,
+    StagedOperationCheckpoint,
+    StagedOperationTail,
+}
+
+// Group-state storage callback trait for Swift-backed persistence of OpenMLS state
+#[uniffi::export(callback_interface)]
+pub trait GroupStateStorage: Send + Sync {
+    /// Write a value for `(group_id, entity_type, key)`
+    ///
+    /// Returns `Err` if the host's backing store rejects the write, e.g. a
+    /// Keychain/SQLite/CoreData failure. That error surfaces as whatever
+    /// `MLSError` the failing operation reports (the staged registry reports
+    /// `StorageError`, with this error in its detail); it is not mapped to
+    /// `MLSError::StorageFailed`
+    fn write(&self, group_id: Vec<u8>, entity_type: GroupStorageEntityType, key: Vec<u8>, value: Vec<u8>) -> Result<(), crate::error::StorageCallbackError>;
+
+    /// Read the value stored for `(group_id, entity_type, key)`, if any
+    fn read(&self, group_id: Vec<u8>, entity_type: GroupStorageEntityType, key: Vec<u8>) -> Option<Vec<u8>>;
+
+    /// Delete the value stored for `(group_id, entity_type, key)`
+    ///
+    /// Deleting a key that was never written is not an error; only a genuine
+    /// backing-store failure should return `Err`
+    fn delete(&self, group_id: Vec<u8>, entity_type: GroupStorageEntityType, key: Vec<u8>) -> Result<(), crate::error::StorageCallbackError>;
+}
+
 // Epoch secret storage callback trait for Swift encrypted storage
 #[uniffi::export(callback_interface)]
 pub trait EpochSecretStorage: Send + Sync {
@@ -169,18 +427,74 @@ pub trait EpochSecretStorage: Send + Sync {
     /// - conversation_id: Hex-encoded conversation/group ID
     /// - epoch: Epoch number
     /// - secret_data: Serialized epoch secret material
-    /// Returns true if stored successfully
-    fn store_epoch_secret(&self, conversation_id: String, epoch: u64, secret_data: Vec<u8>) -> bool;
+    fn store_epoch_secret(&self, conversation_id: String, epoch: u64, secret_data: Vec<u8>) -> Result<(), crate::error::StorageCallbackError>;
 
     /// Retrieve epoch secret for a conversation
     /// - conversation_id: Hex-encoded conversation/group ID
     /// - epoch: Epoch number
-    /// Returns serialized epoch secret material if found
-    fn get_epoch_secret(&self, conversation_id: String, epoch: u64) -> Option<Vec<u8>>;
+    /// Returns `Err(StorageCallbackError::NotFound)` if no secret is stored for this epoch
+    fn get_epoch_secret(&self, conversation_id: String, epoch: u64) -> Result<Vec<u8>, crate::error::StorageCallbackError>;
 
     /// Delete epoch secret (called during retention cleanup)
     /// - conversation_id: Hex-encoded conversation/group ID
     /// - epoch: Epoch number
-    /// Returns true if deleted successfully
-    fn delete_epoch_secret(&self, conversation_id: String, epoch: u64) -> bool;
+    fn delete_epoch_secret(&self, conversation_id: String, epoch: u64) -> Result<(), crate::error::StorageCallbackError>;
+
+    /// List the epochs for which a secret is currently stored
+    /// - conversation_id: Hex-encoded conversation/group ID
+    /// Returns the stored epoch numbers in unspecified order
+    fn list_epoch_secrets(&self, conversation_id: String) -> Result<Vec<u64>, crate::error::StorageCallbackError>;
+
+    /// Delete a batch of epoch secrets in a single call
+    /// - conversation_id: Hex-encoded conversation/group ID
+    /// - epochs: Epoch numbers to remove
+    fn delete_epoch_secrets(&self, conversation_id: String, epochs: Vec<u64>) -> Result<(), crate::error::StorageCallbackError>;
+}
+
+/// An identity's registered signer, as tracked by `signers_by_identity`
+#[derive(uniffi::Record, Clone)]
+pub struct SignerEntry {
+    pub identity: String,
+    pub signer_public_key: Vec<u8>,
+}
+
+/// Bookkeeping for one cached key package bundle - which identity it
+/// belongs to, whether it's last-resort, and its rotation history - kept
+/// separately from the bundle's own private key material, which already has
+/// its own pluggable backend via `GroupStateStorage`/`install_storage_provider`
+#[derive(uniffi::Record, Clone)]
+pub struct KeyPackageMetadataEntry {
+    pub hash_ref: Vec<u8>,
+    pub identity: String,
+    pub last_resort: bool,
+    pub created_at_unix_secs: u64,
+    pub rotation_index: u64,
+    pub retired: bool,
+}
+
+/// Pluggable backend for the identity->signer mapping and key package
+/// bookkeeping (`KeyPackageMetadataEntry`), kept in addition to the copy of
+/// this state that `serialize_storage`/`deserialize_storage` carry in the
+/// monolithic JSON blob
+///
+/// This is deliberately narrower than "every OpenMLS storage key": the
+/// bundle's own private key material already goes through a pluggable
+/// backend (`GroupStateStorage`, keyed by `GroupStorageEntityType::KeyPackage`,
+/// installed via `install_storage_provider`). This trait covers the layer
+/// above that, which OpenMLS's own storage trait has no concept of - which
+/// identity a hash_ref belongs to, whether it's last-resort, and its
+/// rotation history. Once installed via `install_key_package_metadata_storage`,
+/// signer registrations and `rotate_key_packages` bundle bookkeeping are
+/// written through to it as they change; bundles made by `create_key_package`
+/// are not. `serialize_storage`/`deserialize_storage` keep covering this state
+/// either way. Call `restore_key_package_metadata` once at startup to
+/// rehydrate the in-memory caches from this backend.
+#[uniffi::export(callback_interface)]
+pub trait KeyPackageMetadataStorage: Send + Sync {
+    fn put_signer(&self, entry: SignerEntry) -> Result<(), crate::error::StorageCallbackError>;
+    fn list_signers(&self) -> Result<Vec<SignerEntry>, crate::error::StorageCallbackError>;
+
+    fn put_bundle_metadata(&self, entry: KeyPackageMetadataEntry) -> Result<(), crate::error::StorageCallbackError>;
+    fn delete_bundle_metadata(&self, hash_ref: Vec<u8>) -> Result<(), crate::error::StorageCallbackError>;
+    fn list_bundle_metadata(&self) -> Result<Vec<KeyPackageMetadataEntry>, crate::error::StorageCallbackError>;
 }